@@ -0,0 +1,99 @@
+use blake2::{Blake2s256, Digest};
+use crate::math::{FieldElement, Polynomial};
+
+// NONLINEAR COMBINATION
+// ================================================================================================
+
+/// Random combination of several polynomials with individual degree bounds into a single
+/// polynomial of degree at most `max_degree`, so that one FRI run proves all of them low degree.
+///
+/// Every input `q_i` with degree bound `d_i` contributes two terms, `q_i` and
+/// `X^(max_degree - d_i) * q_i`, each with its own weight. The shifted copy lifts `q_i` to the
+/// common bound, so if any single input exceeds its own bound the combination exceeds
+/// `max_degree` except with negligible probability over the weights.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonlinearCombination {
+    pub max_degree: usize,
+    pub degree_bounds: Vec<usize>,
+    pub weights: Vec<FieldElement>,
+}
+
+impl NonlinearCombination {
+
+    pub fn new(max_degree: usize, degree_bounds: &[usize], weights: Vec<FieldElement>) -> Self {
+        assert!(degree_bounds.iter().all(|&d| d <= max_degree), "degree bound exceeds max degree");
+        assert!(weights.len() == Self::num_weights(degree_bounds.len()), "wrong number of weights");
+        NonlinearCombination {
+            max_degree,
+            degree_bounds: degree_bounds.to_vec(),
+            weights,
+        }
+    }
+
+    /// Creates the combination with weights derived from the Fiat-Shamir randomness of the
+    /// transcript, e.g. `proof_stream.prover_fiat_shamir()`.
+    pub fn from_randomness(max_degree: usize, degree_bounds: &[usize], randomness: &[u8]) -> Self {
+        let weights = Self::sample_weights(Self::num_weights(degree_bounds.len()), randomness);
+        Self::new(max_degree, degree_bounds, weights)
+    }
+
+    pub fn num_weights(num_inputs: usize) -> usize {
+        2 * num_inputs
+    }
+
+    pub fn sample_weights(number: usize, randomness: &[u8]) -> Vec<FieldElement> {
+        (0..number)
+            .map(|i| {
+                let mut hasher = Blake2s256::new();
+                hasher.update(randomness);
+                hasher.update((i as u64).to_le_bytes());
+                FieldElement::sample(&hasher.finalize())
+            })
+            .collect()
+    }
+
+    pub fn shifts(&self) -> Vec<usize> {
+        self.degree_bounds.iter().map(|d| self.max_degree - d).collect()
+    }
+
+    /// Combines the input polynomials.
+    pub fn combine_polynomials(&self, polynomials: &[Polynomial]) -> Polynomial {
+        assert!(polynomials.len() == self.degree_bounds.len(), "number of polynomials does not match");
+        let mut combination = Polynomial::zero();
+        for (i, (polynomial, shift)) in polynomials.iter().zip(self.shifts()).enumerate() {
+            let mut shifted = FieldElement::zeroed_vector(shift);
+            shifted.extend_from_slice(polynomial.coefficients());
+            combination = combination + polynomial * self.weights[2 * i];
+            combination = combination + Polynomial::from(shifted) * self.weights[2 * i + 1];
+        }
+        combination
+    }
+
+    /// Combines the codewords of the inputs over `domain` point by point; the result is the
+    /// codeword of `combine_polynomials` over the same domain.
+    pub fn combine_codewords(&self, codewords: &[Vec<FieldElement>], domain: &[FieldElement])
+        -> Vec<FieldElement>
+    {
+        assert!(codewords.len() == self.degree_bounds.len(), "number of codewords does not match");
+        assert!(codewords.iter().all(|c| c.len() == domain.len()), "codeword length does not match");
+        (0..domain.len())
+            .map(|j| {
+                let values = codewords.iter().map(|c| c[j]).collect::<Vec<FieldElement>>();
+                self.evaluate(domain[j], &values)
+            })
+            .collect()
+    }
+
+    /// Computes the value of the combination at `point` from the values of the inputs at that
+    /// point. This is what the verifier checks against the opened combination codeword at every
+    /// query index.
+    pub fn evaluate(&self, point: FieldElement, values: &[FieldElement]) -> FieldElement {
+        assert!(values.len() == self.degree_bounds.len(), "number of values does not match");
+        let mut acc = FieldElement::ZERO;
+        for (i, (&value, shift)) in values.iter().zip(self.shifts()).enumerate() {
+            acc = acc + self.weights[2 * i] * value
+                + self.weights[2 * i + 1] * (point ^ (shift as u128)) * value;
+        }
+        acc
+    }
+}
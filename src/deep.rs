@@ -0,0 +1,176 @@
+use blake2::{Blake2s256, Digest};
+use crate::math::{FieldElement, Polynomial, errors::DeserializationError};
+use crate::combination::NonlinearCombination;
+
+// OUT-OF-DOMAIN FRAME
+// ================================================================================================
+
+/// Evaluations sent by the prover at the out-of-domain point `z`: the trace polynomials at `z`
+/// and at `omicron * z`, and the composition polynomial at `z`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OodFrame {
+    pub current: Vec<FieldElement>,
+    pub next: Vec<FieldElement>,
+    pub composition: FieldElement,
+}
+
+impl OodFrame {
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut elements = self.current.clone();
+        elements.extend_from_slice(&self.next);
+        elements.push(self.composition);
+        FieldElement::elements_as_bytes(&elements).to_vec()
+    }
+
+    pub fn from_bytes(bytes: &[u8], num_registers: usize) -> Result<Self, DeserializationError> {
+        let elements = FieldElement::read_elements(bytes)?;
+        if elements.len() != 2 * num_registers + 1 {
+            return Err(DeserializationError::InvalidValue(format!(
+                "expected {} elements in out-of-domain frame, but got {}",
+                2 * num_registers + 1,
+                elements.len()
+            )));
+        }
+        Ok(OodFrame {
+            current: elements[..num_registers].to_vec(),
+            next: elements[num_registers..2 * num_registers].to_vec(),
+            composition: elements[2 * num_registers],
+        })
+    }
+}
+
+// DEEP COMPOSITION
+// ================================================================================================
+
+/// DEEP-ALI composition: instead of checking the quotients only at the FRI query indices, the
+/// verifier checks the AIR at a random point `z` outside of both the trace domain and the
+/// evaluation domain, and FRI is run on the quotients `(f(X) - f(z)) / (X - z)`, which are low
+/// degree only if the claimed out-of-domain values are correct.
+///
+/// The DEEP quotients of every trace polynomial at `z` and `omicron * z` and of the composition
+/// polynomial at `z` are merged with a `NonlinearCombination`, in this order: all quotients at
+/// `z`, all quotients at `omicron * z`, then the composition quotient.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeepComposition {
+    pub z: FieldElement,
+    pub next_z: FieldElement,
+    pub combination: NonlinearCombination,
+}
+
+impl DeepComposition {
+
+    pub fn new(z: FieldElement, omicron: FieldElement, num_registers: usize, trace_degree: usize,
+        composition_degree: usize, randomness: &[u8]) -> Self
+    {
+        let mut degree_bounds = vec![trace_degree.saturating_sub(1); 2 * num_registers];
+        degree_bounds.push(composition_degree.saturating_sub(1));
+        let max_degree = degree_bounds.iter().fold(0, |acc, &d| acc.max(d));
+        let max_degree = (max_degree + 1).next_power_of_two() - 1;
+        DeepComposition {
+            z,
+            next_z: omicron * z,
+            combination: NonlinearCombination::from_randomness(max_degree, &degree_bounds, randomness),
+        }
+    }
+
+    /// Samples the out-of-domain point from the transcript randomness, drawing again while the
+    /// point lies in the trace domain of order `trace_domain_length` or in the evaluation coset
+    /// `offset * <omega>` of order `eval_domain_length`.
+    pub fn sample_point(randomness: &[u8], trace_domain_length: usize, offset: FieldElement,
+        eval_domain_length: usize) -> FieldElement
+    {
+        let offset_power = offset ^ (eval_domain_length as u128);
+        let mut counter: u64 = 0;
+        loop {
+            let mut hasher = Blake2s256::new();
+            hasher.update(randomness);
+            hasher.update(counter.to_le_bytes());
+            let z = FieldElement::sample(&hasher.finalize());
+            if z ^ (trace_domain_length as u128) != FieldElement::ONE
+                && z ^ (eval_domain_length as u128) != offset_power
+            {
+                return z;
+            }
+            counter += 1;
+        }
+    }
+
+    /// Evaluates the trace polynomials and the composition polynomial at the out-of-domain
+    /// points.
+    pub fn open(&self, trace_polynomials: &[Polynomial], composition: &Polynomial) -> OodFrame {
+        OodFrame {
+            current: trace_polynomials.iter().map(|p| p.evaluate(self.z)).collect(),
+            next: trace_polynomials.iter().map(|p| p.evaluate(self.next_z)).collect(),
+            composition: composition.evaluate(self.z),
+        }
+    }
+
+    /// Returns `(f(X) - f(point)) / (X - point)`, computed by synthetic division.
+    pub fn quotient_polynomial(polynomial: &Polynomial, point: FieldElement) -> Polynomial {
+        let polynomial = polynomial.coefficients();
+        if polynomial.len() < 2 {
+            return Polynomial::zero();
+        }
+        let degree = polynomial.len() - 1;
+        let mut quotient = FieldElement::zeroed_vector(degree);
+        quotient[degree - 1] = polynomial[degree];
+        for i in (1..degree).rev() {
+            quotient[i - 1] = polynomial[i] + point * quotient[i];
+        }
+        Polynomial::from(quotient)
+    }
+
+    /// Returns the codeword of `(f(X) - value) / (X - point)` over `domain`, given the codeword of
+    /// `f`. The domain must not contain `point`.
+    pub fn quotient_codeword(codeword: &[FieldElement], domain: &[FieldElement], point: FieldElement,
+        value: FieldElement) -> Vec<FieldElement>
+    {
+        assert!(codeword.len() == domain.len(), "codeword length does not match domain");
+        let denominators = FieldElement::batch_inverse(
+            &domain.iter().map(|&x| x - point).collect::<Vec<FieldElement>>());
+        codeword.iter().zip(denominators)
+            .map(|(&f, d)| (f - value) * d)
+            .collect()
+    }
+
+    /// Returns the value of `(f(X) - value) / (X - point)` at `x`; used by the verifier at the
+    /// query points.
+    pub fn quotient_value(x: FieldElement, value_at_x: FieldElement, point: FieldElement,
+        value: FieldElement) -> FieldElement
+    {
+        (value_at_x - value) / (x - point)
+    }
+
+    /// Computes the codeword fed to FRI from the codewords of the trace polynomials and of the
+    /// composition polynomial over `domain`.
+    pub fn composition_codeword(&self, domain: &[FieldElement], trace_codewords: &[Vec<FieldElement>],
+        composition_codeword: &[FieldElement], frame: &OodFrame) -> Vec<FieldElement>
+    {
+        let mut quotients = Vec::with_capacity(2 * trace_codewords.len() + 1);
+        for (codeword, &value) in trace_codewords.iter().zip(frame.current.iter()) {
+            quotients.push(Self::quotient_codeword(codeword, domain, self.z, value));
+        }
+        for (codeword, &value) in trace_codewords.iter().zip(frame.next.iter()) {
+            quotients.push(Self::quotient_codeword(codeword, domain, self.next_z, value));
+        }
+        quotients.push(Self::quotient_codeword(composition_codeword, domain, self.z, frame.composition));
+        self.combination.combine_codewords(&quotients, domain)
+    }
+
+    /// Computes the value of the FRI codeword at `x` from the opened trace row and composition
+    /// value at `x`; the verifier compares it against the first FRI layer at every query.
+    pub fn composition_value(&self, x: FieldElement, trace_values: &[FieldElement],
+        composition_value: FieldElement, frame: &OodFrame) -> FieldElement
+    {
+        let mut quotients = Vec::with_capacity(2 * trace_values.len() + 1);
+        for (&f, &value) in trace_values.iter().zip(frame.current.iter()) {
+            quotients.push(Self::quotient_value(x, f, self.z, value));
+        }
+        for (&f, &value) in trace_values.iter().zip(frame.next.iter()) {
+            quotients.push(Self::quotient_value(x, f, self.next_z, value));
+        }
+        quotients.push(Self::quotient_value(x, composition_value, self.z, frame.composition));
+        self.combination.evaluate(x, &quotients)
+    }
+}
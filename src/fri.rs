@@ -1,102 +1,921 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use core::fmt;
 use blake2::{Blake2s256, Digest};
 use crate::proof_stream::ProofStream;
-use crate::math::FieldElement;
+use crate::merkle_tree::{Merkle, MerkleTree};
+use crate::combination::NonlinearCombination;
+use crate::math::{FieldElement, Polynomial, ELEMENT_BYTES};
+use crate::security::SecurityLevel;
+#[cfg(feature = "concurrent")]
+use rayon::prelude::*;
 
+// Number of folded values computed per task; every chunk pays one exponentiation to find its
+// first domain point.
+const FOLD_CHUNK_SIZE: usize = 1024;
+
+// FRI OPTIONS
+// ================================================================================================
+
+/// Defines errors which can occur when validating FRI parameters.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FriConfigError {
+    /// The evaluation domain length is not a power of two.
+    DomainLengthNotPowerOfTwo(u128),
+    /// The evaluation domain is larger than the largest power-of-two subgroup of the field.
+    DomainTooLarge(u128),
+    /// The expansion factor is not a power of two.
+    ExpansionFactorNotPowerOfTwo(u128),
+    /// The expansion factor is smaller than 2, so codewords would carry no redundancy.
+    ExpansionFactorTooSmall(u128),
+    /// The expansion factor is not smaller than the evaluation domain.
+    ExpansionFactorTooLarge { expansion_factor: u128, domain_length: u128 },
+    /// Omega does not generate a subgroup of the same order as the evaluation domain.
+    OmegaWrongOrder { omega: u128, domain_length: u128 },
+    /// The offset lies inside the subgroup, so the evaluation domain is not a proper coset.
+    OffsetInSubgroup(u128),
+    /// The folding factor is not one of 2, 4, 8 or 16.
+    InvalidFoldingFactor(u128),
+    /// The Merkle arity is not one of 2, 4, 8 or 16.
+    InvalidMerkleArity(u128),
+    /// The cap height exceeds the depth of the Merkle tree of the first layer.
+    CapHeightTooLarge { cap_height: u128, depth: u128 },
+    /// More grinding bits were requested than a 64-bit nonce can provide.
+    TooManyGrindingBits(u128),
+    /// No colinearity tests were requested.
+    NoColinearityTests,
+    /// More colinearity tests were requested than the last layer has distinct points.
+    TooManyColinearityTests { num_colinearity_tests: u128, last_layer_length: u128 },
+    /// The domain is too short for the number of colinearity tests to fold it at least once, so
+    /// no layer would be committed and no query would be made.
+    TooFewRounds(u128),
+}
+
+impl fmt::Display for FriConfigError {
+    #[rustfmt::skip]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DomainLengthNotPowerOfTwo(length) => {
+                write!(f, "domain length must be a power of two, but was {}", length)
+            }
+            Self::DomainTooLarge(length) => {
+                write!(f, "domain length cannot exceed 2^119, but was {}", length)
+            }
+            Self::ExpansionFactorNotPowerOfTwo(factor) => {
+                write!(f, "expansion factor must be a power of two, but was {}", factor)
+            }
+            Self::ExpansionFactorTooSmall(factor) => {
+                write!(f, "expansion factor must be at least 2, but was {}", factor)
+            }
+            Self::ExpansionFactorTooLarge { expansion_factor, domain_length } => {
+                write!(f, "expansion factor {} must be smaller than domain length {}", expansion_factor, domain_length)
+            }
+            Self::OmegaWrongOrder { omega, domain_length } => {
+                write!(f, "omega {} is not a primitive root of order {}", omega, domain_length)
+            }
+            Self::OffsetInSubgroup(offset) => {
+                write!(f, "offset {} lies in the subgroup generated by omega", offset)
+            }
+            Self::InvalidFoldingFactor(factor) => {
+                write!(f, "folding factor must be 2, 4, 8 or 16, but was {}", factor)
+            }
+            Self::InvalidMerkleArity(arity) => {
+                write!(f, "Merkle arity must be 2, 4, 8 or 16, but was {}", arity)
+            }
+            Self::CapHeightTooLarge { cap_height, depth } => {
+                write!(f, "cap height {} exceeds the depth {} of the first layer's tree", cap_height, depth)
+            }
+            Self::TooManyGrindingBits(bits) => {
+                write!(f, "grinding bits cannot exceed 64, but were {}", bits)
+            }
+            Self::NoColinearityTests => {
+                write!(f, "at least one colinearity test is required")
+            }
+            Self::TooManyColinearityTests { num_colinearity_tests, last_layer_length } => {
+                write!(f, "cannot run {} colinearity tests on a last layer of length {}", num_colinearity_tests, last_layer_length)
+            }
+            Self::TooFewRounds(num_rounds) => {
+                write!(f, "at least 2 rounds are required, but the parameters give {}", num_rounds)
+            }
+        }
+    }
+}
+
+/// Builder for validated FRI parameters.
+///
+/// Only the domain length, expansion factor and number of colinearity tests are required; omega
+/// defaults to the primitive root of unity of the domain's order and the offset to
+/// `FieldElement::GENERATOR`, which generates the whole multiplicative group and hence lies
+/// outside every proper subgroup.
+pub struct FriOptions {
+    domain_length: u128,
+    expansion_factor: u128,
+    num_colinearity_tests: u128,
+    folding_factor: u128,
+    grinding_bits: u128,
+    last_layer_as_coefficients: bool,
+    cap_height: u128,
+    merkle_arity: u128,
+    offset: Option<u128>,
+    omega: Option<u128>,
+}
+
+impl FriOptions {
+
+    pub fn new(domain_length: u128, expansion_factor: u128, num_colinearity_tests: u128) -> Self {
+        FriOptions {
+            domain_length,
+            expansion_factor,
+            num_colinearity_tests,
+            folding_factor: 2,
+            grinding_bits: 0,
+            last_layer_as_coefficients: false,
+            cap_height: 0,
+            merkle_arity: 2,
+            offset: None,
+            omega: None,
+        }
+    }
+
+    pub fn with_folding_factor(mut self, folding_factor: u128) -> Self {
+        self.folding_factor = folding_factor;
+        self
+    }
+
+    pub fn with_grinding_bits(mut self, grinding_bits: u128) -> Self {
+        self.grinding_bits = grinding_bits;
+        self
+    }
+
+    pub fn with_last_layer_as_coefficients(mut self, last_layer_as_coefficients: bool) -> Self {
+        self.last_layer_as_coefficients = last_layer_as_coefficients;
+        self
+    }
+
+    pub fn with_cap_height(mut self, cap_height: u128) -> Self {
+        self.cap_height = cap_height;
+        self
+    }
+
+    pub fn with_merkle_arity(mut self, merkle_arity: u128) -> Self {
+        self.merkle_arity = merkle_arity;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: u128) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn with_omega(mut self, omega: u128) -> Self {
+        self.omega = Some(omega);
+        self
+    }
+
+    pub fn build(self) -> Result<Fri, FriConfigError> {
+        let domain_length = self.domain_length;
+        if !domain_length.is_power_of_two() {
+            return Err(FriConfigError::DomainLengthNotPowerOfTwo(domain_length));
+        }
+        if domain_length > 1 << 119 {
+            return Err(FriConfigError::DomainTooLarge(domain_length));
+        }
+        if !self.expansion_factor.is_power_of_two() {
+            return Err(FriConfigError::ExpansionFactorNotPowerOfTwo(self.expansion_factor));
+        }
+        if self.expansion_factor < 2 {
+            return Err(FriConfigError::ExpansionFactorTooSmall(self.expansion_factor));
+        }
+        if self.expansion_factor >= domain_length {
+            return Err(FriConfigError::ExpansionFactorTooLarge {
+                expansion_factor: self.expansion_factor,
+                domain_length,
+            });
+        }
+        if self.num_colinearity_tests == 0 {
+            return Err(FriConfigError::NoColinearityTests);
+        }
+        if !matches!(self.folding_factor, 2 | 4 | 8 | 16) {
+            return Err(FriConfigError::InvalidFoldingFactor(self.folding_factor));
+        }
+        if !matches!(self.merkle_arity, 2 | 4 | 8 | 16) {
+            return Err(FriConfigError::InvalidMerkleArity(self.merkle_arity));
+        }
+        let num_leafs = (domain_length / self.folding_factor).max(1) as usize;
+        let depth = MerkleTree::fan_outs(num_leafs, self.merkle_arity as usize).len() as u128;
+        if self.cap_height > depth {
+            return Err(FriConfigError::CapHeightTooLarge { cap_height: self.cap_height, depth });
+        }
+        if self.grinding_bits > 64 {
+            return Err(FriConfigError::TooManyGrindingBits(self.grinding_bits));
+        }
+
+        let omega = match self.omega {
+            Some(omega) => FieldElement::new(omega),
+            None => FieldElement::ZERO.primitive_nth_root(domain_length),
+        };
+        if omega ^ domain_length != FieldElement::ONE
+            || (domain_length > 1 && omega ^ (domain_length / 2) == FieldElement::ONE)
+        {
+            return Err(FriConfigError::OmegaWrongOrder { omega: omega.as_int(), domain_length });
+        }
+
+        let offset = self.offset.map_or(FieldElement::GENERATOR, FieldElement::new);
+        if offset.is_zero() || offset ^ domain_length == FieldElement::ONE {
+            return Err(FriConfigError::OffsetInSubgroup(offset.as_int()));
+        }
+
+        let fri = Fri::new(offset.as_int(), omega.as_int(), domain_length, self.expansion_factor,
+            self.num_colinearity_tests)
+            .with_folding_factor(self.folding_factor)
+            .with_grinding_bits(self.grinding_bits)
+            .with_last_layer_as_coefficients(self.last_layer_as_coefficients)
+            .with_cap_height(self.cap_height)
+            .with_merkle_arity(self.merkle_arity);
+
+        let last_layer_length = fri.last_layer_length();
+        if self.num_colinearity_tests > last_layer_length {
+            return Err(FriConfigError::TooManyColinearityTests {
+                num_colinearity_tests: self.num_colinearity_tests,
+                last_layer_length,
+            });
+        }
+        let num_rounds = fri.num_rounds();
+        if num_rounds < 2 {
+            return Err(FriConfigError::TooFewRounds(num_rounds));
+        }
+
+        Ok(fri)
+    }
+}
+
+// FRI
+// ================================================================================================
+
+/// FRI low degree test over the evaluation domain `{offset * omega^i : 0 <= i < domain_length}`.
+///
+/// Every round folds the codeword by `folding_factor`: the `folding_factor` points of the domain
+/// sharing the same `folding_factor`-th power form a coset of the subgroup of that order, the
+/// values on each coset are interpolated by a polynomial of degree less than `folding_factor`, and
+/// that polynomial is evaluated at the verifier's challenge `alpha`. For a folding factor of 2
+/// this is the usual `(1 + alpha/x) f(x) / 2 + (1 - alpha/x) f(-x) / 2`.
+///
+/// Layers are committed with one Merkle leaf per coset, so that a single authentication path
+/// opens all values needed to compute a folded value.
+///
+/// With `grinding_bits > 0` the prover must find a nonce whose hash with the transcript starts
+/// with that many zero bits before the query indices are drawn, which adds `grinding_bits` to the
+/// cost of every attempt to resample the queries.
+///
+/// The last layer is sent in the clear, either as a codeword or, with
+/// `last_layer_as_coefficients`, as the coefficients of its polynomial: the latter is shorter by
+/// the expansion factor and spares the verifier from interpolating it.
+///
+/// With `cap_height > 0` every layer is committed with a Merkle cap of `2^cap_height` nodes
+/// instead of a single root, which shortens every authentication path by `cap_height` nodes.
+/// `FriOptions` rejects cap heights above the depth of the first layer's tree; the trees of later
+/// layers are shallower, and the cap height is clamped to their depth.
+///
+/// With `merkle_arity > 2` the layers are committed in trees where every node hashes `merkle_arity`
+/// children, trading `merkle_arity - 1` siblings per level for fewer levels; see `MerkleTree`. The
+/// cap height then counts levels of that tree.
 pub struct Fri {
     pub offset: u128,
     pub omega: u128,
     pub domain_length: u128,
     pub expansion_factor: u128,
-    pub num_colinearity_tests: u128
+    pub num_colinearity_tests: u128,
+    pub folding_factor: u128,
+    pub grinding_bits: u128,
+    pub last_layer_as_coefficients: bool,
+    pub cap_height: u128,
+    pub merkle_arity: u128
 }
 
 impl Fri {
 
-    pub fn new(offset: u128, omega: u128, 
+    /// Creates FRI parameters without any validation; see `FriOptions` for a checked builder.
+    pub fn new(offset: u128, omega: u128,
         domain_length: u128, expansion_factor: u128, num_colinearity_tests: u128) -> Self {
         Fri {
             offset,
             omega,
             domain_length,
             expansion_factor,
-            num_colinearity_tests
+            num_colinearity_tests,
+            folding_factor: 2,
+            grinding_bits: 0,
+            last_layer_as_coefficients: false,
+            cap_height: 0,
+            merkle_arity: 2
+        }
+    }
+
+    pub fn with_folding_factor(mut self, folding_factor: u128) -> Self {
+        assert!(matches!(folding_factor, 2 | 4 | 8 | 16), "folding factor must be 2, 4, 8 or 16");
+        self.folding_factor = folding_factor;
+        self
+    }
+
+    pub fn with_grinding_bits(mut self, grinding_bits: u128) -> Self {
+        assert!(grinding_bits <= 64, "grinding bits must not exceed 64");
+        self.grinding_bits = grinding_bits;
+        self
+    }
+
+    pub fn with_last_layer_as_coefficients(mut self, last_layer_as_coefficients: bool) -> Self {
+        self.last_layer_as_coefficients = last_layer_as_coefficients;
+        self
+    }
+
+    pub fn with_cap_height(mut self, cap_height: u128) -> Self {
+        self.cap_height = cap_height;
+        self
+    }
+
+    pub fn with_merkle_arity(mut self, merkle_arity: u128) -> Self {
+        assert!(matches!(merkle_arity, 2 | 4 | 8 | 16), "Merkle arity must be 2, 4, 8 or 16");
+        self.merkle_arity = merkle_arity;
+        self
+    }
+
+    /// Cap height used for a tree over `num_leafs` leaves.
+    pub fn layer_cap_height(&self, num_leafs: usize) -> usize {
+        let depth = MerkleTree::fan_outs(num_leafs, self.merkle_arity as usize).len();
+        (self.cap_height as usize).min(depth)
+    }
+
+    /// Number of nodes in the cap of a tree over `num_leafs` leaves.
+    pub fn layer_cap_length(&self, num_leafs: usize) -> usize {
+        MerkleTree::cap_length(num_leafs, self.merkle_arity as usize, self.layer_cap_height(num_leafs))
+    }
+
+    /// Number of nodes in an authentication path of a tree over `num_leafs` leaves.
+    pub fn layer_path_length(&self, num_leafs: usize) -> usize {
+        MerkleTree::path_length(num_leafs, self.merkle_arity as usize, self.layer_cap_height(num_leafs))
+    }
+
+    fn layer_tree(&self, leafs: &[Vec<u8>]) -> MerkleTree {
+        MerkleTree::with_arity(leafs, self.merkle_arity as usize)
+    }
+
+    /// Conjectured security level in bits: every colinearity test contributes
+    /// `log2(expansion_factor)` bits and grinding adds `grinding_bits` on top, capped by the field
+    /// size and the hash output size.
+    pub fn conjectured_security_level(&self) -> u128 {
+        self.security_level().conjectured as u128
+    }
+
+    /// Conjectured and proven security levels; see the `security` module.
+    pub fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::of(self)
+    }
+
+    /// Number of bytes the prover pushes into the proof stream.
+    pub fn proof_size(&self) -> usize {
+        let folding_factor = self.folding_factor as usize;
+        let num_rounds = self.num_rounds() as usize;
+        let last_layer_length = self.last_layer_length() as usize;
+
+        let mut size = 0;
+        let mut codeword_length = self.domain_length as usize;
+        for _ in 1..num_rounds {
+            size += 32 * self.layer_cap_length(codeword_length / folding_factor);
+            codeword_length /= folding_factor;
+        }
+        size += ELEMENT_BYTES * if self.last_layer_as_coefficients {
+            last_layer_length / self.expansion_factor as usize
+        } else {
+            last_layer_length
+        };
+        if num_rounds < 2 {
+            return size;
+        }
+        if self.grinding_bits > 0 {
+            size += 8;
         }
+
+        let mut codeword_length = self.domain_length as usize;
+        for _ in 1..num_rounds {
+            let num_leafs = codeword_length / folding_factor;
+            let path_length = self.layer_path_length(num_leafs) * 32;
+            size += self.num_colinearity_tests as usize * (folding_factor * ELEMENT_BYTES + path_length);
+            codeword_length = num_leafs;
+        }
+        size
+    }
+
+    /// Largest degree of a polynomial whose codeword passes the test.
+    pub fn max_degree(&self) -> usize {
+        (self.domain_length / self.expansion_factor) as usize - 1
     }
 
     pub fn num_rounds(&self) -> u128 {
         let mut codeword_length = self.domain_length;
         let mut num_rounds: u128 = 0;
-        while (codeword_length > self.expansion_factor) && 
+        while (codeword_length > self.expansion_factor) &&
                 (4*self.num_colinearity_tests < codeword_length) {
-            codeword_length /= 2;
+            codeword_length /= self.folding_factor;
             num_rounds += 1;
         }
         num_rounds
     }
 
-    pub fn eval_domain(&self) -> Vec<u128>{
+    /// Length of the last layer, which is sent in the clear.
+    pub fn last_layer_length(&self) -> u128 {
+        self.domain_length / self.folding_factor.pow(self.num_rounds().saturating_sub(1) as u32)
+    }
+
+    pub fn eval_domain(&self) -> Vec<FieldElement> {
+        let offset = FieldElement::new(self.offset);
+        let omega = FieldElement::new(self.omega);
         (0..self.domain_length)
-            .map(|i| self.offset * (self.omega ^ i))
-            .collect::<Vec<u128>>()
+            .map(|i| offset * (omega ^ i))
+            .collect()
+    }
+
+    pub fn sample_index(bytes: &[u8], size: usize) -> usize {
+        let mut acc = [0u8; 8];
+        acc.copy_from_slice(&bytes[..8]);
+        (u64::from_le_bytes(acc) % size as u64) as usize
+    }
+
+    /// Samples `number` indices in `[0, size)` which are pairwise distinct modulo `reduced_size`.
+    pub fn sample_indices(seed: &[u8], size: usize, reduced_size: usize, number: usize) -> Vec<usize> {
+        assert!(number <= reduced_size, "cannot sample more indices than available in last codeword; requested: {}, available: {}", number, reduced_size);
+
+        let mut indices = Vec::with_capacity(number);
+        let mut reduced_indices = Vec::with_capacity(number);
+        let mut counter: u64 = 0;
+        while indices.len() < number {
+            let mut hasher = Blake2s256::new();
+            hasher.update(seed);
+            hasher.update(counter.to_le_bytes());
+            let index = Self::sample_index(&hasher.finalize(), size);
+            let reduced_index = index % reduced_size;
+            counter += 1;
+            if !reduced_indices.contains(&reduced_index) {
+                indices.push(index);
+                reduced_indices.push(reduced_index);
+            }
+        }
+        indices
     }
 
-    pub fn prove(&self, codeword: &[[u8]], proof_stream: &ProofStream) -> {
-        assert!(self.domain_length == codeword.len(), "initial codeword length does not match");
-        let codewords = Self::commit(codeword, &proof_stream);
-        let top_level_indices = Self::sample_indices(proof_stream.prover_fiat_shamir(), 
-            codewords[1].len(), codewords.last().len(), self.num_colinearity_tests);
+    pub fn prove(&self, codeword: &[FieldElement], proof_stream: &mut ProofStream) -> Vec<usize> {
+        assert!(self.domain_length as usize == codeword.len(), "initial codeword length does not match");
+        let (codewords, trees) = self.commit(codeword, proof_stream);
+        if codewords.len() < 2 {
+            return Vec::new();
+        }
+
+        if self.grinding_bits > 0 {
+            let nonce = Self::grind(&proof_stream.prover_fiat_shamir(), self.grinding_bits);
+            proof_stream.push(nonce.to_le_bytes().to_vec());
+        }
+
+        let top_level_indices = Self::sample_indices(&proof_stream.prover_fiat_shamir(),
+            codewords[1].len(), codewords.last().unwrap().len(), self.num_colinearity_tests as usize);
         let mut indices = top_level_indices.clone();
         for i in 0..(codewords.len()-1) {
-            indices = indices.iter().map(|index| index % (codewords[i].len()/2)).collect();
-            Self::query(&self, codewords[i], codewords[i+1], &indices, &proof_stream);
+            indices = indices.iter().map(|index| index % codewords[i+1].len()).collect();
+            self.query(&codewords[i], &trees[i], &indices, proof_stream);
         }
         top_level_indices
+    }
+
+    /// Commits to all layers and returns their codewords, together with the trees of all layers
+    /// but the last one, for `query` to open.
+    pub fn commit(&self, codeword: &[FieldElement], proof_stream: &mut ProofStream)
+        -> (Vec<Vec<FieldElement>>, Vec<MerkleTree>)
+    {
+        let mut omega = FieldElement::new(self.omega);
+        let mut offset = FieldElement::new(self.offset);
+        let mut codeword = codeword.to_vec();
+        let mut codewords = Vec::new();
+        let mut trees = Vec::new();
+
+        // the last codeword is sent in the clear, so only the layers before it are committed
+        for _ in 1..self.num_rounds() {
+            let tree = self.layer_tree(&self.leafs(&codeword));
+            proof_stream.push(tree.cap(self.layer_cap_height(tree.num_leafs())).concat());
+            trees.push(tree);
+
+            let alpha = FieldElement::sample(&proof_stream.prover_fiat_shamir());
+            let folded = self.fold(&codeword, omega, offset, alpha);
+            codewords.push(codeword);
+            codeword = folded;
+
+            omega = omega ^ self.folding_factor;
+            offset = offset ^ self.folding_factor;
+        }
+
+        if self.last_layer_as_coefficients {
+            // padded to the full length, so that the proof size does not depend on the codeword;
+            // a codeword of too high a degree leaves coefficients that cannot be sent
+            let mut coefficients = Polynomial::interpolate_coset(&codeword, offset, omega).into_coefficients();
+            let length = codeword.len() / self.expansion_factor as usize;
+            assert!(coefficients.iter().skip(length).all(FieldElement::is_zero),
+                "last layer exceeds the degree bound; the codeword is not of low degree");
+            coefficients.resize(length, FieldElement::ZERO);
+            proof_stream.push(FieldElement::elements_as_bytes(&coefficients).to_vec());
+        } else {
+            proof_stream.push(FieldElement::elements_as_bytes(&codeword).to_vec());
+        }
+        codewords.push(codeword);
+        (codewords, trees)
+    }
 
+    /// Opens the cosets at `indices` of a codeword committed in `tree`.
+    pub fn query(&self, current_codeword: &[FieldElement], tree: &MerkleTree, indices: &[usize],
+        proof_stream: &mut ProofStream)
+    {
+        for &index in indices {
+            proof_stream.push(self.leaf(current_codeword, index));
+        }
+        for &index in indices {
+            proof_stream.push(tree.prove_to_cap(index, self.layer_cap_height(tree.num_leafs())).concat());
+        }
     }
 
-    pub fn commit(&self, codeword, proof_stream, round_index) Vec<Vec<u8>> {
-        let one = FieldElement::ONE;
-        let two = FieldElement::new(2);
-        let mut omega = self.omega;
-        let mut offset = self.offset;
-        let mut codewords: Vec<Vec<u8>>;
-        
-        for r in 0..self.num_rounds(){
+    /// Verifies the proof in the stream; on success `polynomial_values` holds the opened
+    /// `(index, value)` pairs of the initial codeword.
+    pub fn verify(&self, proof_stream: &mut ProofStream, polynomial_values: &mut Vec<(usize, FieldElement)>) -> bool {
+        self.verify_(proof_stream, polynomial_values).is_some()
+    }
 
-            let root = Merkle.commit(codeword);
-            proof_stream.push(root);
+    // returns the top level indices of the queries if the proof is valid
+    fn verify_(&self, proof_stream: &mut ProofStream, polynomial_values: &mut Vec<(usize, FieldElement)>) -> Option<Vec<usize>> {
+        let folding_factor = self.folding_factor as usize;
+        let num_rounds = self.num_rounds() as usize;
+        let mut omega = FieldElement::new(self.omega);
+        let mut offset = FieldElement::new(self.offset);
 
-            if r == self.num_rounds() - 1{
-                break;
+        // extract all caps and alphas
+        let mut caps = Vec::new();
+        let mut alphas = Vec::new();
+        let mut codeword_length = self.domain_length as usize;
+        for _ in 1..num_rounds {
+            codeword_length /= folding_factor;
+            let cap = match Self::read_path(&proof_stream.try_pull().ok()?) {
+                Some(cap) if cap.len() == self.layer_cap_length(codeword_length) => cap,
+                _ => return None,
+            };
+            caps.push(cap);
+            alphas.push(FieldElement::sample(&proof_stream.verifier_fiat_shamir()));
+        }
+
+        // extract last layer, either as codeword or as coefficients
+        let last_layer = match FieldElement::read_elements(&proof_stream.try_pull().ok()?) {
+            Ok(layer) => layer,
+            Err(_) => return None,
+        };
+        let last_length = self.last_layer_length() as usize;
+        let degree = last_length / self.expansion_factor as usize - 1;
+        let last_omega = omega ^ (self.domain_length / last_length as u128);
+        let last_offset = offset ^ (self.domain_length / last_length as u128);
+
+        // check if it is low degree; coefficients are low degree by their number alone
+        if self.last_layer_as_coefficients {
+            if last_layer.len() > degree + 1 {
+                return None;
+            }
+        } else {
+            if last_layer.len() != last_length {
+                return None;
+            }
+            let poly = Polynomial::interpolate_coset(&last_layer, last_offset, last_omega);
+            if poly.degree().is_some_and(|d| d > degree) {
+                return None;
+            }
+        }
+
+        if num_rounds < 2 {
+            return Some(Vec::new());
+        }
+
+        // check proof of work
+        if self.grinding_bits > 0 {
+            let seed = proof_stream.verifier_fiat_shamir();
+            let nonce = match proof_stream.try_pull().ok()?.try_into() {
+                Ok(bytes) => u64::from_le_bytes(bytes),
+                Err(_) => return None,
+            };
+            if !Self::check_nonce(&seed, nonce, self.grinding_bits) {
+                return None;
+            }
+        }
+
+        // get indices
+        let top_level_indices = Self::sample_indices(&proof_stream.verifier_fiat_shamir(),
+            self.domain_length as usize / folding_factor, last_length, self.num_colinearity_tests as usize);
+
+        // for every layer, open the cosets and fold them
+        let mut indices = top_level_indices.clone();
+        let mut expected = Vec::new();
+        let mut codeword_length = self.domain_length as usize;
+        for r in 0..(num_rounds - 1) {
+            let num_leafs = codeword_length / folding_factor;
+            let path_length = self.layer_path_length(num_leafs);
+            let previous_indices = indices;
+            indices = previous_indices.iter().map(|index| index % num_leafs).collect();
+
+            let mut leafs = Vec::with_capacity(indices.len());
+            for _ in 0..indices.len() {
+                leafs.push(proof_stream.try_pull().ok()?);
+            }
+            for s in 0..indices.len() {
+                let path = match Self::read_path(&proof_stream.try_pull().ok()?) {
+                    Some(path) if path.len() == path_length => path,
+                    _ => return None,
+                };
+                if !Merkle::verify_with_arity(self.merkle_arity as usize, num_leafs, &caps[r], indices[s], &path, &leafs[s]) {
+                    return None;
+                }
             }
-            let alpha = FieldElement::sample(proof_stream.prover_fiat_shamir());
 
-            codewords.append(codeword);
+            let k_inv = FieldElement::new(self.folding_factor).inverse();
+            let zeta_inv = (omega ^ num_leafs as u128).inverse();
+            let mut folded = Vec::with_capacity(indices.len());
+            for s in 0..indices.len() {
+                let values = match FieldElement::read_elements(&leafs[s]) {
+                    Ok(values) if values.len() == folding_factor => values,
+                    _ => return None,
+                };
 
-            codeword = (0..codeword.len()/2).iter().map(
-                |i| two.inverse() * ( (one + alpha / (offset * (omega^i)) ) 
-                * codeword[i] + (one - alpha / (offset * (omega^i)) ) 
-                * codeword[len(codeword)/2 + i] )
-            ).collect();
+                if r == 0 {
+                    for (t, &value) in values.iter().enumerate() {
+                        polynomial_values.push((indices[s] + t * num_leafs, value));
+                    }
+                } else if values[previous_indices[s] / num_leafs] != expected[s] {
+                    return None;
+                }
 
-            omega = omega ^ 2;
-            offset = offset ^ 2;
+                let x_inv = (offset * (omega ^ indices[s] as u128)).inverse();
+                folded.push(k_inv * Self::fold_coset(&values, x_inv, alphas[r], zeta_inv));
+            }
+            expected = folded;
+
+            omega = omega ^ self.folding_factor;
+            offset = offset ^ self.folding_factor;
+            codeword_length = num_leafs;
+        }
+
+        // the last fold must agree with the last layer sent in the clear
+        let last_polynomial = self.last_layer_as_coefficients.then(|| Polynomial::from(last_layer.clone()));
+        for s in 0..indices.len() {
+            let value = match &last_polynomial {
+                Some(polynomial) => polynomial.evaluate(last_offset * (last_omega ^ indices[s] as u128)),
+                None => last_layer[indices[s]],
+            };
+            if value != expected[s] {
+                return None;
+            }
         }
 
+        Some(top_level_indices)
+    }
+
+    // BATCHED FRI
+    // --------------------------------------------------------------------------------------------
 
-        proof_stream.push(codeword);
+    /// Proves that every `codewords[i]` belongs to a polynomial of degree at most
+    /// `degree_bounds[i]` with a single FRI run.
+    ///
+    /// All codewords are committed first. The batching coefficients are then drawn from the
+    /// transcript, and FRI is run on their `NonlinearCombination` with `max_degree()` as the
+    /// common bound. At the FRI query indices every original codeword is opened, so the verifier
+    /// can recompute the combination from the openings.
+    pub fn prove_batch(&self, codewords: &[Vec<FieldElement>], degree_bounds: &[usize],
+        proof_stream: &mut ProofStream) -> Vec<usize>
+    {
+        assert!(codewords.len() == degree_bounds.len(), "number of degree bounds does not match");
+        assert!(self.num_rounds() >= 2, "batched FRI needs at least 2 rounds to open the codewords");
+        let mut trees = Vec::with_capacity(codewords.len());
+        for codeword in codewords {
+            assert!(self.domain_length as usize == codeword.len(), "codeword length does not match");
+            let tree = self.layer_tree(&self.leafs(codeword));
+            proof_stream.push(tree.cap(self.layer_cap_height(tree.num_leafs())).concat());
+            trees.push(tree);
+        }
 
-        codewords.append(codeword);
+        let combination = NonlinearCombination::from_randomness(self.max_degree(), degree_bounds,
+            &proof_stream.prover_fiat_shamir());
+        let combined = combination.combine_codewords(codewords, &self.eval_domain());
+        let top_level_indices = self.prove(&combined, proof_stream);
 
-        codewords
+        for (codeword, tree) in codewords.iter().zip(trees.iter()) {
+            self.query(codeword, tree, &top_level_indices, proof_stream);
+        }
+        top_level_indices
     }
 
-    pub fn query(&self, current_codeword, next_codeword, c_indices, proof_stream) -> {
-        let a_indices = c_indices.clone();
-        let b_indices = c_indices.iter().map(|index| index + current_codeword.len()/2).collect();
+    /// Verifies a batched proof; on success `polynomial_values[i]` holds the opened
+    /// `(index, value)` pairs of the `i`-th codeword.
+    pub fn verify_batch(&self, degree_bounds: &[usize], proof_stream: &mut ProofStream,
+        polynomial_values: &mut Vec<Vec<(usize, FieldElement)>>) -> bool
+    {
+        // with a single round no query is made, so the codewords would never be opened
+        if self.num_rounds() < 2 {
+            return false;
+        }
+        let folding_factor = self.folding_factor as usize;
+        let num_leafs = self.domain_length as usize / folding_factor;
+        let cap_length = self.layer_cap_length(num_leafs);
+        let path_length = self.layer_path_length(num_leafs);
+
+        let mut caps = Vec::with_capacity(degree_bounds.len());
+        for _ in 0..degree_bounds.len() {
+            let cap = match proof_stream.try_pull().ok().and_then(|bytes| Self::read_path(&bytes)) {
+                Some(cap) if cap.len() == cap_length => cap,
+                _ => return false,
+            };
+            caps.push(cap);
+        }
+        let combination = NonlinearCombination::from_randomness(self.max_degree(), degree_bounds,
+            &proof_stream.verifier_fiat_shamir());
+
+        let mut combined_values = Vec::new();
+        let indices = match self.verify_(proof_stream, &mut combined_values) {
+            Some(indices) => indices,
+            None => return false,
+        };
+
+        // open every codeword at the cosets queried by FRI
+        let mut opened = Vec::with_capacity(caps.len());
+        for cap in caps {
+            let mut leafs = Vec::with_capacity(indices.len());
+            for _ in 0..indices.len() {
+                match proof_stream.try_pull() {
+                    Ok(leaf) => leafs.push(leaf),
+                    Err(_) => return false,
+                }
+            }
+            let mut values = Vec::with_capacity(combined_values.len());
+            for s in 0..indices.len() {
+                let path = match proof_stream.try_pull().ok().and_then(|bytes| Self::read_path(&bytes)) {
+                    Some(path) if path.len() == path_length => path,
+                    _ => return false,
+                };
+                if !Merkle::verify_with_arity(self.merkle_arity as usize, num_leafs, &cap, indices[s], &path, &leafs[s]) {
+                    return false;
+                }
+                let coset = match FieldElement::read_elements(&leafs[s]) {
+                    Ok(coset) if coset.len() == folding_factor => coset,
+                    _ => return false,
+                };
+                for (t, value) in coset.into_iter().enumerate() {
+                    values.push((indices[s] + t * num_leafs, value));
+                }
+            }
+            opened.push(values);
+        }
 
-        for s in 0..self.num_colinearity_tests {
-            proof_stream.push((current_codeword[]))
+        // the openings must combine to the values of the first FRI layer
+        let offset = FieldElement::new(self.offset);
+        let omega = FieldElement::new(self.omega);
+        for (p, &(index, value)) in combined_values.iter().enumerate() {
+            let values = opened.iter().map(|o| o[p].1).collect::<Vec<FieldElement>>();
+            if combination.evaluate(offset * (omega ^ index as u128), &values) != value {
+                return false;
+            }
         }
+
+        polynomial_values.append(&mut opened);
+        true
+    }
+
+    // PROOF OF WORK
+    // --------------------------------------------------------------------------------------------
+
+    /// Returns the smallest nonce for which `Blake2s(seed || nonce)` starts with `grinding_bits`
+    /// zero bits. The search is split over all available cores; every thread keeps going until
+    /// its candidates exceed the best nonce found so far, so the result does not depend on
+    /// scheduling. Panics if no 64-bit nonce qualifies.
+    pub fn grind(seed: &[u8], grinding_bits: u128) -> u64 {
+        let num_threads = thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+        let found = AtomicU64::new(u64::MAX);
+        thread::scope(|scope| {
+            for first in 0..num_threads {
+                let found = &found;
+                scope.spawn(move || {
+                    let mut nonce = first;
+                    while nonce < found.load(Ordering::Relaxed) {
+                        if Self::check_nonce(seed, nonce, grinding_bits) {
+                            found.fetch_min(nonce, Ordering::Relaxed);
+                            break;
+                        }
+                        // a thread whose candidates run past `u64::MAX` has nothing left to try
+                        match nonce.checked_add(num_threads) {
+                            Some(next) => nonce = next,
+                            None => break,
+                        }
+                    }
+                });
+            }
+        });
+        // the threads never try `u64::MAX`, which doubles as "not found", so it is checked here
+        let nonce = found.into_inner();
+        assert!(Self::check_nonce(seed, nonce, grinding_bits), "no nonce has {} leading zero bits", grinding_bits);
+        nonce
     }
-}
\ No newline at end of file
+
+    pub fn check_nonce(seed: &[u8], nonce: u64, grinding_bits: u128) -> bool {
+        let mut hasher = Blake2s256::new();
+        hasher.update(seed);
+        hasher.update(nonce.to_le_bytes());
+        let digest = hasher.finalize();
+
+        let mut leading_zeros = 0;
+        for byte in digest.iter() {
+            leading_zeros += byte.leading_zeros() as u128;
+            if *byte != 0 {
+                break;
+            }
+        }
+        leading_zeros >= grinding_bits
+    }
+
+    // HELPER FUNCTIONS
+    // --------------------------------------------------------------------------------------------
+
+    /// Groups the codeword into cosets of the subgroup of order `folding_factor`: leaf `j` holds
+    /// the values at indices `j, j + n, j + 2n, ...` where `n = codeword.len() / folding_factor`.
+    fn leafs(&self, codeword: &[FieldElement]) -> Vec<Vec<u8>> {
+        let num_leafs = codeword.len() / self.folding_factor as usize;
+        (0..num_leafs).map(|j| self.leaf(codeword, j)).collect()
+    }
+
+    // the `j`-th leaf of `leafs`
+    fn leaf(&self, codeword: &[FieldElement], j: usize) -> Vec<u8> {
+        let num_leafs = codeword.len() / self.folding_factor as usize;
+        let coset = (0..self.folding_factor as usize)
+            .map(|t| codeword[j + t * num_leafs])
+            .collect::<Vec<FieldElement>>();
+        FieldElement::elements_as_bytes(&coset).to_vec()
+    }
+
+    fn fold(&self, codeword: &[FieldElement], omega: FieldElement, offset: FieldElement,
+        alpha: FieldElement) -> Vec<FieldElement>
+    {
+        let folding_factor = self.folding_factor as usize;
+        let num_leafs = codeword.len() / folding_factor;
+        let k_inv = FieldElement::new(self.folding_factor).inverse();
+        let zeta_inv = (omega ^ num_leafs as u128).inverse();
+        let omega_inv = omega.inverse();
+        let offset_inv = offset.inverse();
+
+        // the folded codeword is filled in chunks, each starting from its own power of omega,
+        // so that the chunks can be computed independently
+        let fold_chunk = |(c, chunk): (usize, &mut [FieldElement])| {
+            let first = c * FOLD_CHUNK_SIZE;
+            let mut x_inv = offset_inv * (omega_inv ^ first as u128);
+            for (j, folded) in (first..).zip(chunk.iter_mut()) {
+                let values = (0..folding_factor)
+                    .map(|t| codeword[j + t * num_leafs])
+                    .collect::<Vec<FieldElement>>();
+                *folded = k_inv * Self::fold_coset(&values, x_inv, alpha, zeta_inv);
+                x_inv = x_inv * omega_inv;
+            }
+        };
+
+        let mut folded = FieldElement::zeroed_vector(num_leafs);
+        #[cfg(not(feature = "concurrent"))]
+        folded.chunks_mut(FOLD_CHUNK_SIZE).enumerate().for_each(fold_chunk);
+        #[cfg(feature = "concurrent")]
+        folded.par_chunks_mut(FOLD_CHUNK_SIZE).enumerate().for_each(fold_chunk);
+        folded
+    }
+
+    /// Given the values of a function on the coset `x * <zeta>` of the subgroup of order `k`,
+    /// returns `k` times the value at `alpha` of the polynomial of degree less than `k`
+    /// interpolating them. The interpolant's coefficients follow from an inverse DFT:
+    /// `c_s = x^(-s) / k * sum_t values[t] * zeta^(-s * t)`.
+    fn fold_coset(values: &[FieldElement], x_inv: FieldElement, alpha: FieldElement,
+        zeta_inv: FieldElement) -> FieldElement
+    {
+        let ratio = alpha * x_inv;
+        let mut ratio_power = FieldElement::ONE;
+        let mut zeta_power = FieldElement::ONE;
+        let mut acc = FieldElement::ZERO;
+        for _ in 0..values.len() {
+            let mut coefficient = FieldElement::ZERO;
+            let mut root = FieldElement::ONE;
+            for &value in values {
+                coefficient = coefficient + value * root;
+                root = root * zeta_power;
+            }
+            acc = acc + coefficient * ratio_power;
+            ratio_power = ratio_power * ratio;
+            zeta_power = zeta_power * zeta_inv;
+        }
+        acc
+    }
+
+    fn read_path(bytes: &[u8]) -> Option<Vec<[u8; 32]>> {
+        if !bytes.len().is_multiple_of(32) {
+            return None;
+        }
+        Some(bytes.chunks(32).map(|chunk| chunk.try_into().unwrap()).collect())
+    }
+}
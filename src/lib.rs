@@ -4,6 +4,20 @@ pub use math::*;
 pub mod proof_stream;
 pub use proof_stream::ProofStream;
 pub mod merkle_tree;
-pub use merkle_tree::Merkle;
+pub use merkle_tree::{Merkle, MerkleTree, NodeStorage, RowMerkleTree, SaltedMerkleTree};
+#[cfg(unix)]
+pub use merkle_tree::FileStorage;
+pub mod sparse_merkle_tree;
+pub use sparse_merkle_tree::{SparseMerkleProof, SparseMerkleTree};
 pub mod fri;
-pub use fri::Fri;
\ No newline at end of file
+pub use fri::{Fri, FriConfigError, FriOptions};
+pub mod combination;
+pub use combination::NonlinearCombination;
+pub mod deep;
+pub use deep::{DeepComposition, OodFrame};
+pub mod proof;
+pub use proof::{FriProof, Opening, StarkProof};
+pub mod security;
+pub use security::SecurityLevel;
+pub mod stark;
+pub use stark::Stark;
\ No newline at end of file
@@ -0,0 +1,320 @@
+use core::fmt;
+use super::field_element::FieldElement;
+use super::m_polynomial::MPolynomial;
+use super::M;
+
+// CONSTRAINT PARSER
+// ================================================================================================
+
+/// Parses transition constraints written as expressions over the registers of an execution
+/// trace, e.g. `x1' - x0*x1 + 3*x2^7`, into `MPolynomial`s.
+///
+/// Variables are laid out the way `Stark` expects transition constraints: `X` is the domain
+/// variable `x0`, register `xi` of the current row is the variable `x(1 + i)` and register `xi'`
+/// of the next row is the variable `x(1 + num_registers + i)`.
+///
+/// Expressions are built from non-negative integer constants, which are reduced modulo `M`,
+/// variables, whose register index is written without leading zeros, parentheses, `+`, binary and unary `-`, `*` and `^` with an integer exponent. `^`
+/// binds tighter than unary `-`, so `-x0^2` is `-(x0^2)`. Whitespace is ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConstraintParser {
+    num_registers: usize,
+}
+
+/// Error returned when an expression cannot be parsed; `position` is the byte offset of the
+/// offending token in the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A token was found where it cannot occur; `expected` describes what could have followed.
+    UnexpectedToken { found: String, expected: &'static str },
+    /// The input ended in the middle of an expression.
+    UnexpectedEnd { expected: &'static str },
+    /// A register index is not smaller than the number of registers.
+    UnknownRegister(usize),
+    /// An exponent, as written or in the expanded polynomial, does not fit into a `u128`.
+    ExponentTooLarge,
+}
+
+impl ConstraintParser {
+
+    pub fn new(num_registers: usize) -> Self {
+        ConstraintParser { num_registers }
+    }
+
+    pub fn num_registers(&self) -> usize {
+        self.num_registers
+    }
+
+    pub fn parse(&self, input: &str) -> Result<MPolynomial, ParseError> {
+        let mut parser = Parser { input, position: 0, num_registers: self.num_registers };
+        let polynomial = parser.expression()?;
+        match parser.peek() {
+            None => Ok(polynomial),
+            Some((position, token)) => Err(unexpected(position, token, "an operator or the end of the input")),
+        }
+    }
+
+    /// Parses one constraint per item.
+    pub fn parse_all<'a, I>(&self, inputs: I) -> Result<Vec<MPolynomial>, ParseError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        inputs.into_iter().map(|input| self.parse(input)).collect()
+    }
+
+    /// Returns a wrapper writing `polynomial` in the syntax accepted by `parse`, so that parsing
+    /// the written string gives back the same polynomial.
+    pub fn display<'a>(&self, polynomial: &'a MPolynomial) -> ConstraintDisplay<'a> {
+        assert!(polynomial.num_variables() <= 1 + 2 * self.num_registers,
+            "polynomial has more variables than the registers provide");
+        ConstraintDisplay { polynomial, num_registers: self.num_registers }
+    }
+}
+
+// DISPLAY
+// ================================================================================================
+
+/// Writes a polynomial as a constraint over named registers; see `ConstraintParser::display`.
+pub struct ConstraintDisplay<'a> {
+    polynomial: &'a MPolynomial,
+    num_registers: usize,
+}
+
+impl fmt::Display for ConstraintDisplay<'_> {
+    /// Writes the terms in the order of `MPolynomial::terms`, e.g. `3 + x0^2*x1' - 5*X`;
+    /// coefficients above `M / 2` are written as subtracted terms.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.polynomial.is_zero() {
+            return write!(f, "0");
+        }
+        for (i, (exponents, coefficient)) in self.polynomial.terms().iter().enumerate() {
+            let (negative, magnitude) = if coefficient.as_int() > M / 2 {
+                (true, (-*coefficient).as_int())
+            } else {
+                (false, coefficient.as_int())
+            };
+            match (i, negative) {
+                (0, false) => {}
+                (0, true) => write!(f, "-")?,
+                (_, false) => write!(f, " + ")?,
+                (_, true) => write!(f, " - ")?,
+            }
+
+            let mut factors = Vec::new();
+            if magnitude != 1 || exponents.is_empty() {
+                factors.push(magnitude.to_string());
+            }
+            for (variable, &e) in exponents.iter().enumerate().filter(|&(_, &e)| e > 0) {
+                let name = if variable == 0 {
+                    "X".to_string()
+                } else if variable <= self.num_registers {
+                    format!("x{}", variable - 1)
+                } else {
+                    format!("x{}'", variable - 1 - self.num_registers)
+                };
+                factors.push(if e == 1 { name } else { format!("{}^{}", name, e) });
+            }
+            write!(f, "{}", factors.join("*"))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: ", self.position)?;
+        match &self.kind {
+            ParseErrorKind::UnexpectedToken { found, expected } => {
+                write!(f, "unexpected `{}`, expected {}", found, expected)
+            }
+            ParseErrorKind::UnexpectedEnd { expected } => {
+                write!(f, "unexpected end of input, expected {}", expected)
+            }
+            ParseErrorKind::UnknownRegister(index) => {
+                write!(f, "register x{} does not exist", index)
+            }
+            ParseErrorKind::ExponentTooLarge => {
+                write!(f, "exponent does not fit into 128 bits")
+            }
+        }
+    }
+}
+
+// PARSER
+// ================================================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Number(&'a str),
+    Variable(&'a str),
+    Symbol(char),
+}
+
+fn unexpected(position: usize, token: Token, expected: &'static str) -> ParseError {
+    ParseError {
+        position,
+        kind: ParseErrorKind::UnexpectedToken { found: token.to_string(), expected },
+    }
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(s) | Token::Variable(s) => write!(f, "{}", s),
+            Token::Symbol(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+const OPERAND: &str = "a number, a register, `X`, `-` or `(`";
+
+// recursive-descent parser over
+//   expression = term (("+" | "-") term)*
+//   term       = unary ("*" unary)*
+//   unary      = "-" unary | power
+//   power      = atom ("^" number)?
+//   atom       = number | "X" | "x" digits "'"? | "(" expression ")"
+// where the digits of a register index have no leading zeros
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    num_registers: usize,
+}
+
+impl<'a> Parser<'a> {
+
+    fn expression(&mut self) -> Result<MPolynomial, ParseError> {
+        let mut acc = self.term()?;
+        while let Some((_, Token::Symbol(op @ ('+' | '-')))) = self.peek() {
+            self.next();
+            let rhs = self.term()?;
+            acc = if op == '+' { acc + rhs } else { acc - rhs };
+        }
+        Ok(acc)
+    }
+
+    fn term(&mut self) -> Result<MPolynomial, ParseError> {
+        let mut acc = self.unary()?;
+        while let Some((position, Token::Symbol('*'))) = self.peek() {
+            self.next();
+            acc = acc.checked_mul(&self.unary()?).ok_or(ParseError {
+                position,
+                kind: ParseErrorKind::ExponentTooLarge,
+            })?;
+        }
+        Ok(acc)
+    }
+
+    fn unary(&mut self) -> Result<MPolynomial, ParseError> {
+        if let Some((_, Token::Symbol('-'))) = self.peek() {
+            self.next();
+            return Ok(-self.unary()?);
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<MPolynomial, ParseError> {
+        let base = self.atom()?;
+        if let Some((_, Token::Symbol('^'))) = self.peek() {
+            self.next();
+            return match self.next() {
+                Some((position, Token::Number(digits))) => {
+                    let too_large = ParseError { position, kind: ParseErrorKind::ExponentTooLarge };
+                    let exponent = digits.parse::<u128>().map_err(|_| too_large.clone())?;
+                    base.checked_pow(exponent).ok_or(too_large)
+                }
+                Some((position, token)) => Err(unexpected(position, token, "an exponent")),
+                None => Err(self.end("an exponent")),
+            };
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<MPolynomial, ParseError> {
+        match self.next() {
+            Some((_, Token::Number(digits))) => {
+                let value = digits.bytes().fold(FieldElement::ZERO, |acc, d| {
+                    acc * FieldElement::new(10) + FieldElement::new((d - b'0') as u128)
+                });
+                Ok(MPolynomial::constant(value))
+            }
+            Some((position, Token::Variable(name))) => self.variable(position, name),
+            Some((_, Token::Symbol('('))) => {
+                let inner = self.expression()?;
+                match self.next() {
+                    Some((_, Token::Symbol(')'))) => Ok(inner),
+                    Some((position, token)) => Err(unexpected(position, token, "an operator or `)`")),
+                    None => Err(self.end("`)`")),
+                }
+            }
+            Some((position, token)) => Err(unexpected(position, token, OPERAND)),
+            None => Err(self.end(OPERAND)),
+        }
+    }
+
+    // `name` is `X` or `x` followed by digits and an optional `'`
+    fn variable(&self, position: usize, name: &str) -> Result<MPolynomial, ParseError> {
+        let mut exponents = vec![0; 1 + 2 * self.num_registers];
+        if name == "X" {
+            exponents[0] = 1;
+            return Ok(MPolynomial::new([(exponents, FieldElement::ONE)]));
+        }
+        let next = name.ends_with('\'');
+        let digits = name[1..].trim_end_matches('\'');
+        if digits.len() > 1 && digits.starts_with('0') {
+            return Err(unexpected(position, Token::Variable(name), "a register index without leading zeros"));
+        }
+        let index = digits.parse::<usize>().ok().filter(|&i| i < self.num_registers).ok_or(ParseError {
+            position,
+            kind: ParseErrorKind::UnknownRegister(digits.parse().unwrap_or(usize::MAX)),
+        })?;
+        exponents[1 + index + if next { self.num_registers } else { 0 }] = 1;
+        Ok(MPolynomial::new([(exponents, FieldElement::ONE)]))
+    }
+
+    // TOKENS
+    // --------------------------------------------------------------------------------------------
+
+    fn peek(&self) -> Option<(usize, Token<'a>)> {
+        let rest = &self.input[self.position..];
+        let start = self.position + (rest.len() - rest.trim_start().len());
+        let rest = &self.input[start..];
+        let c = rest.chars().next()?;
+        let length = if c.is_ascii_digit() {
+            rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len())
+        } else if c == 'x' {
+            let digits = rest[1..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - 1);
+            1 + digits + rest[1 + digits..].starts_with('\'') as usize
+        } else {
+            c.len_utf8()
+        };
+        let text = &rest[..length];
+        let token = match c {
+            '0'..='9' => Token::Number(text),
+            'x' if length > 1 && text.as_bytes()[1].is_ascii_digit() => Token::Variable(text),
+            'X' => Token::Variable(text),
+            // operators, parentheses and any other character, which the grammar then rejects
+            _ => Token::Symbol(c),
+        };
+        Some((start, token))
+    }
+
+    fn next(&mut self) -> Option<(usize, Token<'a>)> {
+        let (start, token) = self.peek()?;
+        self.position = start + match token {
+            Token::Number(s) | Token::Variable(s) => s.len(),
+            Token::Symbol(c) => c.len_utf8(),
+        };
+        Some((start, token))
+    }
+
+    fn end(&self, expected: &'static str) -> ParseError {
+        ParseError { position: self.input.len(), kind: ParseErrorKind::UnexpectedEnd { expected } }
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use super::field_element::FieldElement;
+use super::m_polynomial::MPolynomial;
+#[cfg(feature = "concurrent")]
+use rayon::prelude::*;
+
+// CONSTRAINT PROGRAM
+// ================================================================================================
+
+/// Straight-line program evaluating a set of multivariate polynomials, e.g. the transition
+/// constraints, at many points.
+///
+/// Compiling the polynomials into one program shares work between all terms and polynomials:
+/// every register holds the value of a distinct subexpression, so equal constants, powers of a
+/// variable and monomials sharing a prefix of their exponent vector are computed once per point.
+/// Powers are built by squaring and multiplying from cached smaller powers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintProgram {
+    num_inputs: usize,
+    instructions: Vec<Instruction>,
+    outputs: Vec<usize>,
+}
+
+/// A single step of a `ConstraintProgram`; operands name the registers written by earlier
+/// instructions, and every instruction writes the register of its own index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Input(usize),
+    Constant(FieldElement),
+    Add(usize, usize),
+    Mul(usize, usize),
+}
+
+impl ConstraintProgram {
+
+    /// Compiles `polynomials` into a program over `num_inputs` variables, which must cover the
+    /// variables of every polynomial.
+    pub fn compile(polynomials: &[MPolynomial], num_inputs: usize) -> Self {
+        assert!(polynomials.iter().all(|p| p.num_variables() <= num_inputs),
+            "polynomial has more variables than the program has inputs");
+        let mut compiler = Compiler::default();
+        let outputs = polynomials.iter().map(|polynomial| compiler.polynomial(polynomial)).collect();
+        ConstraintProgram {
+            num_inputs,
+            instructions: compiler.instructions,
+            outputs,
+        }
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn num_multiplications(&self) -> usize {
+        self.instructions.iter().filter(|i| matches!(i, Instruction::Mul(..))).count()
+    }
+
+    /// Returns the value of every polynomial at `row`.
+    pub fn evaluate(&self, row: &[FieldElement]) -> Vec<FieldElement> {
+        self.evaluate_with(row, &mut Vec::new())
+    }
+
+    /// Evaluates the program at every row; with the `concurrent` feature the rows are spread
+    /// over threads.
+    pub fn evaluate_rows(&self, rows: &[Vec<FieldElement>]) -> Vec<Vec<FieldElement>> {
+        #[cfg(not(feature = "concurrent"))]
+        let values = {
+            let mut registers = Vec::new();
+            rows.iter().map(|row| self.evaluate_with(row, &mut registers)).collect()
+        };
+        #[cfg(feature = "concurrent")]
+        let values = rows.par_iter()
+            .map_init(Vec::new, |registers, row| self.evaluate_with(row, registers))
+            .collect();
+        values
+    }
+
+    // `registers` is scratch space, reused between rows to avoid allocating
+    fn evaluate_with(&self, row: &[FieldElement], registers: &mut Vec<FieldElement>) -> Vec<FieldElement> {
+        assert!(row.len() == self.num_inputs, "row length does not match number of inputs");
+        registers.clear();
+        for instruction in self.instructions.iter() {
+            let value = match *instruction {
+                Instruction::Input(i) => row[i],
+                Instruction::Constant(c) => c,
+                Instruction::Add(a, b) => registers[a] + registers[b],
+                Instruction::Mul(a, b) => registers[a] * registers[b],
+            };
+            registers.push(value);
+        }
+        self.outputs.iter().map(|&r| registers[r]).collect()
+    }
+}
+
+// COMPILER
+// ================================================================================================
+
+// key of an instruction for common-subexpression elimination; operands of commutative
+// operations are sorted
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    Input(usize),
+    Constant(u128),
+    Add(usize, usize),
+    Mul(usize, usize),
+}
+
+#[derive(Default)]
+struct Compiler {
+    instructions: Vec<Instruction>,
+    registers: HashMap<Key, usize>,
+    // register holding the monomial of every compiled exponent vector
+    monomials: HashMap<Vec<u128>, usize>,
+}
+
+impl Compiler {
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        let key = match instruction {
+            Instruction::Input(i) => Key::Input(i),
+            Instruction::Constant(c) => Key::Constant(c.as_int()),
+            Instruction::Add(a, b) => Key::Add(a.min(b), a.max(b)),
+            Instruction::Mul(a, b) => Key::Mul(a.min(b), a.max(b)),
+        };
+        if let Some(&register) = self.registers.get(&key) {
+            return register;
+        }
+        self.instructions.push(instruction);
+        let register = self.instructions.len() - 1;
+        self.registers.insert(key, register);
+        register
+    }
+
+    fn polynomial(&mut self, polynomial: &MPolynomial) -> usize {
+        let mut sum = None;
+        for (exponents, &coefficient) in polynomial.terms().iter() {
+            let term = if exponents.is_empty() {
+                self.emit(Instruction::Constant(coefficient))
+            } else {
+                let monomial = self.monomial(exponents);
+                if coefficient == FieldElement::ONE {
+                    monomial
+                } else {
+                    let c = self.emit(Instruction::Constant(coefficient));
+                    self.emit(Instruction::Mul(c, monomial))
+                }
+            };
+            sum = Some(match sum {
+                Some(sum) => self.emit(Instruction::Add(sum, term)),
+                None => term,
+            });
+        }
+        sum.unwrap_or_else(|| self.emit(Instruction::Constant(FieldElement::ZERO)))
+    }
+
+    // multiplies the monomial of the exponent vector without its last non-zero exponent by the
+    // power of that variable, so that monomials sharing a prefix share registers
+    fn monomial(&mut self, exponents: &[u128]) -> usize {
+        if let Some(&register) = self.monomials.get(exponents) {
+            return register;
+        }
+        let last = exponents.len() - 1;
+        let power = self.power(last, exponents[last]);
+        let mut prefix = exponents[..last].to_vec();
+        while prefix.last() == Some(&0) {
+            prefix.pop();
+        }
+        let register = if prefix.is_empty() {
+            power
+        } else {
+            let prefix = self.monomial(&prefix);
+            self.emit(Instruction::Mul(prefix, power))
+        };
+        self.monomials.insert(exponents.to_vec(), register);
+        register
+    }
+
+    // `x{variable}^exponent` by square-and-multiply; intermediate powers are monomials too
+    fn power(&mut self, variable: usize, exponent: u128) -> usize {
+        let mut exponents = vec![0; variable + 1];
+        exponents[variable] = exponent;
+        if let Some(&register) = self.monomials.get(&exponents) {
+            return register;
+        }
+        let register = if exponent == 1 {
+            self.emit(Instruction::Input(variable))
+        } else if exponent.is_multiple_of(2) {
+            let half = self.power(variable, exponent / 2);
+            self.emit(Instruction::Mul(half, half))
+        } else {
+            let x = self.power(variable, 1);
+            let rest = self.power(variable, exponent - 1);
+            self.emit(Instruction::Mul(rest, x))
+        };
+        self.monomials.insert(exponents, register);
+        register
+    }
+}
@@ -28,6 +28,17 @@ impl FieldElement {
         FieldElement(if value < M { value } else { value - M })
     }
 
+    pub const fn as_int(&self) -> u128 {
+        self.0
+    }
+
+    /// Maps a byte string to a field element by reading it as a big-endian integer reduced
+    /// modulo M; used to derive challenges from hash digests and random bytes.
+    pub fn sample(bytes: &[u8]) -> Self {
+        bytes.iter()
+            .fold(Self::ZERO, |acc, &byte| acc * Self::new(256) + Self::new(byte as u128))
+    }
+
     pub fn inverse(&self) -> FieldElement {
         FieldElement(inv(self.0))
     }
@@ -58,8 +69,11 @@ impl FieldElement {
         unsafe { slice::from_raw_parts(p as *const u8, len) }
     }
 
+    /// # Safety
+    /// The returned elements alias `bytes` without checking that the values are in canonical
+    /// form; callers must only pass bytes produced by `elements_as_bytes`.
     pub unsafe fn bytes_as_elements(bytes: &[u8]) -> Result<&[Self], DeserializationError> {
-        if bytes.len() % Self::ELEMENT_BYTES != 0 {
+        if !bytes.len().is_multiple_of(Self::ELEMENT_BYTES) {
             return Err(DeserializationError::InvalidValue(format!(
                 "number of bytes ({}) does not divide into whole number of field elements",
                 bytes.len(),
@@ -69,7 +83,7 @@ impl FieldElement {
         let p = bytes.as_ptr();
         let len = bytes.len() / Self::ELEMENT_BYTES;
 
-        if (p as usize) % mem::align_of::<u128>() != 0 {
+        if !(p as usize).is_multiple_of(mem::align_of::<u128>()) {
             return Err(DeserializationError::InvalidValue(
                 "slice memory alignment is not valid for this field element type".to_string(),
             ));
@@ -78,6 +92,54 @@ impl FieldElement {
         Ok(slice::from_raw_parts(p as *const Self, len))
     }
 
+    /// Copies elements out of a byte slice of arbitrary alignment, such as an object pulled from
+    /// a proof stream, rejecting values which are not in canonical form.
+    pub fn read_elements(bytes: &[u8]) -> Result<Vec<Self>, DeserializationError> {
+        if !bytes.len().is_multiple_of(Self::ELEMENT_BYTES) {
+            return Err(DeserializationError::InvalidValue(format!(
+                "number of bytes ({}) does not divide into whole number of field elements",
+                bytes.len(),
+            )));
+        }
+
+        bytes.chunks(Self::ELEMENT_BYTES)
+            .map(|chunk| {
+                let value = u128::from_le_bytes(chunk.try_into().unwrap());
+                if value >= M {
+                    return Err(DeserializationError::InvalidValue(format!(
+                        "invalid field element: value {} is greater than or equal to the field modulus",
+                        value
+                    )));
+                }
+                Ok(FieldElement(value))
+            })
+            .collect()
+    }
+
+    /// Inverts all elements at once using Montgomery's trick, i.e. with a single field
+    /// inversion and 3(n - 1) multiplications. Zero elements are mapped to zero.
+    pub fn batch_inverse(values: &[Self]) -> Vec<Self> {
+        let mut result = Vec::with_capacity(values.len());
+        let mut acc = Self::ONE;
+        for &value in values {
+            result.push(acc);
+            if !value.is_zero() {
+                acc = acc * value;
+            }
+        }
+
+        acc = acc.inverse();
+        for i in (0..values.len()).rev() {
+            if values[i].is_zero() {
+                result[i] = Self::ZERO;
+                continue;
+            }
+            result[i] = result[i] * acc;
+            acc = acc * values[i];
+        }
+        result
+    }
+
     pub fn generator(&self) -> FieldElement {
         FieldElement::new(85408008396924667383611388730472331217)
     }
@@ -88,7 +150,7 @@ impl FieldElement {
         let mut order: u128 = 1 << 119;
         while order != n {
             root = root ^ 2;
-            order = order / 2;
+            order /= 2;
         }
         root
     }
@@ -136,8 +198,17 @@ impl Neg for FieldElement {
 
 impl BitXor<u128> for FieldElement {
     type Output = Self;
+
+    /// Raises the element to the given power using square-and-multiply.
     fn bitxor(self, exponent: u128) -> FieldElement {
-        Self(self.0 ^ exponent)
+        let mut acc = FieldElement::ONE;
+        for i in (0..(128 - exponent.leading_zeros())).rev() {
+            acc = acc * acc;
+            if (exponent >> i) & 1 == 1 {
+                acc = acc * self;
+            }
+        }
+        acc
     }
 }
 
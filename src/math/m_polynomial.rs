@@ -1,186 +1,262 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::ops::*;
 use super::field_element::FieldElement;
 use super::polynomial::Polynomial;
-use std::cmp;
 
+// MULTIVARIATE POLYNOMIAL
+// ================================================================================================
 
-#[derive(Clone, Debug)]
-pub struct MPolynomial {}
+/// Multivariate polynomial over the base field, stored as a map from exponent vectors to
+/// coefficients; `[2, 0, 1] -> c` is the term `c * x0^2 * x2`.
+///
+/// The representation is canonical: exponent vectors carry no trailing zeros, so the constant
+/// term has the empty vector and a term does not depend on how many variables the polynomial is
+/// thought to have, and zero coefficients are pruned. Terms are kept in a `BTreeMap`, so they are
+/// always iterated in the same order, e.g. when a constraint system is hashed into a transcript.
+///
+/// Exponents are `u128`s. Multiplying or raising to a power panics when an exponent of the
+/// result does not fit; `checked_mul` and `checked_pow` return `None` instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MPolynomial {
+    terms: BTreeMap<Vec<u128>, FieldElement>,
+}
 
 impl MPolynomial {
 
-    // pub fn new(map: &HashMap<Vec<u128>, FieldElement>) -> Self {
-    //     MPolynomial{
-    //         map: map.clone()
-    //     }
-    // }
-
-    pub fn zero() -> HashMap<Vec<u128>, FieldElement> {
-        HashMap::new()
-    }
-
-    pub fn num_variables(left: &HashMap<Vec<u128>, FieldElement>, 
-        right: &HashMap<Vec<u128>, FieldElement>) -> usize {
-        
-        cmp::max(left.keys().map(|x| x.len()).max().unwrap_or(0), right.keys().map(|x| x.len()).max().unwrap_or(0))
-        
-        
-    }
-
-    pub fn add(left: &HashMap<Vec<u128>, FieldElement>, 
-        right: &HashMap<Vec<u128>, FieldElement>) 
-            -> HashMap<Vec<u128>, FieldElement> 
-            {
-    
-        let mut res: HashMap<Vec<u128>, FieldElement> = HashMap::new();
-        let num_variables = Self::num_variables(&left, &right);
-        for (k, v) in left.iter() {
-            let mut pad = k.to_vec();
-            pad.append(&mut vec![0; num_variables - k.len()]);
-            res.insert(pad, *v);
+    /// Builds the polynomial from `(exponents, coefficient)` pairs, adding up the coefficients of
+    /// equal exponent vectors.
+    pub fn new<I>(terms: I) -> Self
+    where
+        I: IntoIterator<Item = (Vec<u128>, FieldElement)>,
+    {
+        let mut polynomial = Self::zero();
+        for (exponents, coefficient) in terms {
+            polynomial.add_term(exponents, coefficient);
         }
-        for (k, v) in right.iter() {
-            let mut pad = k.to_vec();
-            pad.append(&mut vec![0; num_variables - k.len()]);
-            res.insert(pad.clone(), *v + if res.contains_key(&pad) 
-                { res[&pad] } else { FieldElement::new(0) });
-        }     
-        res 
-    }
-
-    pub fn mul(left: &HashMap<Vec<u128>, FieldElement>, 
-        right: &HashMap<Vec<u128>, FieldElement>) 
-            -> HashMap<Vec<u128>, FieldElement> 
-            {
-    
-        let mut res: HashMap<Vec<u128>, FieldElement> = HashMap::new();
-        let num_variables = Self::num_variables(&left, &right);
-        for (k0, v0) in left.iter() {
-            for (k1, v1) in right.iter() {
-                let mut exponent = vec![0; num_variables];
-                for k in 0..k0.len() {
-                    exponent[k] += k0[k]
-                }
-                for k in 0..k1.len() {
-                    exponent[k] += k1[k]
-                }
-                res.insert(exponent.clone(), 
-                    (*v0)*(*v1) + if res.contains_key(&exponent) { res[&exponent] }
-                    else { FieldElement::new(0) });                
+        polynomial
+    }
+
+    pub fn zero() -> Self {
+        MPolynomial::default()
+    }
+
+    pub fn constant(element: FieldElement) -> Self {
+        Self::new([(Vec::new(), element)])
+    }
+
+    /// Returns the variables `x0, ..., x(num_variables - 1)`.
+    pub fn variables(num_variables: usize) -> Vec<Self> {
+        (0..num_variables)
+            .map(|i| {
+                let mut exponents = vec![0; i + 1];
+                exponents[i] = 1;
+                Self::new([(exponents, FieldElement::ONE)])
+            })
+            .collect()
+    }
+
+    /// Returns `poly` as a polynomial in the variable `x{variable_index}`.
+    pub fn lift(poly: &Polynomial, variable_index: usize) -> Self {
+        Self::new(poly.coefficients().iter().enumerate().map(|(i, &coefficient)| {
+            let mut exponents = vec![0; variable_index + 1];
+            exponents[variable_index] = i as u128;
+            (exponents, coefficient)
+        }))
+    }
+
+    pub fn terms(&self) -> &BTreeMap<Vec<u128>, FieldElement> {
+        &self.terms
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Number of variables the polynomial depends on, counting up to the last one that occurs.
+    pub fn num_variables(&self) -> usize {
+        self.terms.keys().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Total degree of the polynomial, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.terms.keys().map(|exponents| exponents.iter().sum::<u128>() as usize).max()
+    }
+
+    /// Evaluates the polynomial at `point`, which needs at least `num_variables()` coordinates.
+    pub fn evaluate(&self, point: &[FieldElement]) -> FieldElement {
+        assert!(point.len() >= self.num_variables(), "point has fewer coordinates than variables");
+        let mut acc = FieldElement::ZERO;
+        for (exponents, &coefficient) in self.terms.iter() {
+            let mut prod = coefficient;
+            for (&x, &e) in point.iter().zip(exponents) {
+                prod = prod * (x ^ e);
             }
+            acc = acc + prod;
         }
-        res
-    }
-
-    pub fn sub(left: &HashMap<Vec<u128>, FieldElement>, 
-        right: &HashMap<Vec<u128>, FieldElement>) -> HashMap<Vec<u128>, FieldElement> {
-        
-        let mut res: HashMap<Vec<u128>, FieldElement> = HashMap::new();
-        let num_variables = Self::num_variables(&left, &right);
-        for (k, v) in left.iter() {
-            let mut pad = k.to_vec();
-            pad.append(&mut vec![0; num_variables - k.len()]);
-            res.insert(pad, *v);
-        }
-        for (k, v) in right.iter() {
-            let mut pad = k.to_vec();
-            pad.append(&mut vec![0; num_variables - k.len()]);
-            res.insert(pad.clone(), FieldElement::new(0) - *v + if res.contains_key(&pad) 
-                { res[&pad] } else { FieldElement::new(0) });
-        }     
-        res 
+        acc
     }
 
-    pub fn xor(left: &HashMap<Vec<u128>, FieldElement>, 
-        exponent: usize) -> HashMap<Vec<u128>, FieldElement> {
-
-        
-        if Self::is_zero(&left) {
-            return HashMap::new();
+    /// Substitutes the univariate polynomial `point[i]` for every variable `x{i}`, e.g. the
+    /// interpolated trace columns into a transition constraint, which gives the univariate
+    /// transition polynomial.
+    ///
+    /// For every variable, the powers occurring in some term are computed once, each from the
+    /// next smaller one, and shared between the terms.
+    pub fn evaluate_symbolic(&self, point: &[Polynomial]) -> Polynomial {
+        assert!(point.len() >= self.num_variables(), "point has fewer coordinates than variables");
+        let mut powers = vec![BTreeMap::new(); self.num_variables()];
+        for exponents in self.terms.keys() {
+            for (i, &e) in exponents.iter().enumerate().filter(|&(_, &e)| e > 0) {
+                powers[i].insert(e, Polynomial::zero());
+            }
+        }
+        for (variable_powers, x) in powers.iter_mut().zip(point) {
+            let mut previous = (0, Polynomial::constant(FieldElement::ONE));
+            for (&e, power) in variable_powers.iter_mut() {
+                *power = &previous.1 * (x ^ (e - previous.0));
+                previous = (e, power.clone());
+            }
         }
-        let num_variables = left.keys().next().unwrap().len();
-        let exp = vec![0; num_variables];
-        let mut acc: HashMap<Vec<u128>, FieldElement> = HashMap::new();
-        acc.insert(exp, FieldElement::ONE);
-        for i in 128..=0 {
-            acc = Self::mul(&acc, &acc);
-            if (1 << i) & exponent != 0 {
-                acc = Self::mul(&acc, &left);
+
+        let mut acc = Polynomial::zero();
+        for (exponents, &coefficient) in self.terms.iter() {
+            let mut prod = Polynomial::constant(coefficient);
+            for (i, e) in exponents.iter().enumerate().filter(|&(_, &e)| e > 0) {
+                prod = prod * &powers[i][e];
             }
+            acc = acc + prod;
         }
         acc
     }
 
-    pub fn constant(element: FieldElement) -> HashMap<Vec<u128>, FieldElement> {
-        let mut map: HashMap<Vec<u128>, FieldElement> = HashMap::new();
-        map.insert(vec![0], element);
-        map
+    /// Returns the product, or `None` if an exponent of the product does not fit into a `u128`.
+    pub fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        let mut product = Self::zero();
+        for (left, &a) in self.terms.iter() {
+            for (right, &b) in rhs.terms.iter() {
+                let mut exponents = left.clone();
+                exponents.resize(left.len().max(right.len()), 0);
+                for (e, &r) in exponents.iter_mut().zip(right) {
+                    *e = e.checked_add(r)?;
+                }
+                product.add_term(exponents, a * b);
+            }
+        }
+        Some(product)
     }
 
-    pub fn is_zero(left: &HashMap<Vec<u128>, FieldElement>) -> bool {
-        for v in left.values() {
-            if !FieldElement::is_zero(&v){
-                return false;
+    /// Raises the polynomial to the given power using square-and-multiply, or returns `None` if
+    /// an exponent of the result does not fit into a `u128`.
+    pub fn checked_pow(&self, exponent: u128) -> Option<Self> {
+        let mut acc = Self::constant(FieldElement::ONE);
+        for i in (0..(128 - exponent.leading_zeros())).rev() {
+            acc = acc.checked_mul(&acc)?;
+            if (exponent >> i) & 1 == 1 {
+                acc = acc.checked_mul(self)?;
             }
         }
-        return true;
-    }
-
-    pub fn variables(num_variables: usize) -> Vec<HashMap<Vec<u128>, FieldElement>> {
-        let mut variables: Vec<HashMap<Vec<u128>, FieldElement>> = Vec::new();
-        for i in 0..num_variables {
-            let mut exponent: Vec<u128> = vec![0; i];
-            exponent.append(&mut vec![1]);
-            exponent.append(&mut vec![0; num_variables - i - 1]);
-            let mut tmp_hash = HashMap::new();
-            tmp_hash.insert(exponent, FieldElement::ONE);
-            variables.push(tmp_hash);
-        }
-        variables
+        Some(acc)
     }
 
-    pub fn lift(poly: &[FieldElement], 
-        variable_index: usize) -> HashMap<Vec<u128>, FieldElement> {
-        
-        if Polynomial::is_zero(&poly) {
-            return HashMap::new();
+    fn add_term(&mut self, mut exponents: Vec<u128>, coefficient: FieldElement) {
+        while exponents.last() == Some(&0) {
+            exponents.pop();
         }
-        let variables = Self::variables(variable_index +1);
-        let x = variables.last().expect("no last on variables");
-        let mut acc: HashMap<Vec<u128>, FieldElement> = HashMap::new();
-        for i in 0..poly.len() {
-            let tmp_hash = Self::mul(&Self::constant(poly[i]), &Self::xor(&x, i));
-            acc = Self::add(&acc, &tmp_hash);
+        let sum = self.terms.get(&exponents).copied().unwrap_or(FieldElement::ZERO) + coefficient;
+        if sum.is_zero() {
+            self.terms.remove(&exponents);
+        } else {
+            self.terms.insert(exponents, sum);
         }
-        acc
     }
+}
 
-    pub fn evaluate(left: &HashMap<Vec<u128>, FieldElement>, point: Vec<FieldElement>) 
-        -> FieldElement {
-        let mut acc: FieldElement = FieldElement::ZERO;
-        for (k, v) in left.iter() {
-            let mut prod = *v;
-            for i in 0..k.len(){
-                prod = prod * (point[i] ^ k[i]);
-            }
-            acc = acc + prod;
+// OPERATORS
+// ================================================================================================
+
+impl Add<&MPolynomial> for &MPolynomial {
+    type Output = MPolynomial;
+
+    fn add(self, rhs: &MPolynomial) -> MPolynomial {
+        let mut sum = self.clone();
+        for (exponents, &coefficient) in rhs.terms.iter() {
+            sum.add_term(exponents.clone(), coefficient);
         }
-        acc
+        sum
+    }
+}
+
+impl Sub<&MPolynomial> for &MPolynomial {
+    type Output = MPolynomial;
+
+    fn sub(self, rhs: &MPolynomial) -> MPolynomial {
+        self + &(-rhs)
+    }
+}
+
+impl Mul<&MPolynomial> for &MPolynomial {
+    type Output = MPolynomial;
+
+    fn mul(self, rhs: &MPolynomial) -> MPolynomial {
+        self.checked_mul(rhs).expect("exponent of the product does not fit into a u128")
     }
+}
+
+forward_binary_op!(MPolynomial, Add, add);
+forward_binary_op!(MPolynomial, Sub, sub);
+forward_binary_op!(MPolynomial, Mul, mul);
 
-    // pub fn evaluate_symbolic(left: &HashMap<Vec<u128>, FieldElement>, point: Vec<FieldElement>) 
-    //     -> Vec<FieldElement>{
-        
-    //     let mut acc: Vec<FieldElement> = Vec::new();
-    //     for (k, v) in left.iter() {
-    //         let mut prod = vec![*v];
-    //         for i in 0..k.len() {
-    //             prod = Polynomial::mul(&prod, point[i] ^ k[i]);
-    //         }
-    //         acc = Polynomial::add(&acc, &prod);
-    //     }
-    //     acc
-    // }
+impl Neg for &MPolynomial {
+    type Output = MPolynomial;
+
+    fn neg(self) -> MPolynomial {
+        MPolynomial { terms: self.terms.iter().map(|(e, &c)| (e.clone(), -c)).collect() }
+    }
+}
 
+impl Neg for MPolynomial {
+    type Output = MPolynomial;
 
-}
\ No newline at end of file
+    fn neg(self) -> MPolynomial {
+        -&self
+    }
+}
+
+impl Mul<FieldElement> for &MPolynomial {
+    type Output = MPolynomial;
+
+    fn mul(self, rhs: FieldElement) -> MPolynomial {
+        MPolynomial::new(self.terms.iter().map(|(e, &c)| (e.clone(), c * rhs)))
+    }
+}
+
+impl Mul<FieldElement> for MPolynomial {
+    type Output = MPolynomial;
+
+    fn mul(self, rhs: FieldElement) -> MPolynomial {
+        &self * rhs
+    }
+}
+
+impl BitXor<u128> for &MPolynomial {
+    type Output = MPolynomial;
+
+    /// Raises the polynomial to the given power; see `checked_pow`.
+    fn bitxor(self, exponent: u128) -> MPolynomial {
+        self.checked_pow(exponent).expect("exponent of the power does not fit into a u128")
+    }
+}
+
+impl BitXor<u128> for MPolynomial {
+    type Output = MPolynomial;
+
+    fn bitxor(self, exponent: u128) -> MPolynomial {
+        &self ^ exponent
+    }
+}
+
+impl From<FieldElement> for MPolynomial {
+    fn from(element: FieldElement) -> Self {
+        MPolynomial::constant(element)
+    }
+}
@@ -1,11 +1,50 @@
+// implements `T op T`, `T op &T` and `&T op T` in terms of `&T op &T`
+macro_rules! forward_binary_op {
+    ($type:ident, $trait:ident, $method:ident) => {
+        impl $trait<$type> for $type {
+            type Output = $type;
+
+            fn $method(self, rhs: $type) -> $type {
+                (&self).$method(&rhs)
+            }
+        }
+
+        impl $trait<&$type> for $type {
+            type Output = $type;
+
+            fn $method(self, rhs: &$type) -> $type {
+                (&self).$method(rhs)
+            }
+        }
+
+        impl $trait<$type> for &$type {
+            type Output = $type;
+
+            fn $method(self, rhs: $type) -> $type {
+                self.$method(&rhs)
+            }
+        }
+    };
+}
+
 mod utils;
 mod field_element;
 mod polynomial;
 pub mod errors;
 mod m_polynomial;
+mod zerofier;
+mod constraint_program;
+mod constraint_parser;
+
+pub use field_element::FieldElement;
+pub use polynomial::Polynomial;
+pub use m_polynomial::MPolynomial;
+pub use zerofier::Zerofier;
+pub use constraint_program::{ConstraintProgram, Instruction};
+pub use constraint_parser::{ConstraintDisplay, ConstraintParser, ParseError, ParseErrorKind};
 
 
-// Field modulus = 2^128 - 45 * 2^40 + 1
+// Field modulus = 1 + 407 * 2^119
 pub const M: u128 = 270497897142230380135924736767050121217;
 
 // Number of bytes needed to represent field element
@@ -1,191 +1,394 @@
-use super::{field_element::FieldElement};
-use core;
-pub struct Polynomial {}
+use core::fmt;
+use std::ops::*;
+use super::field_element::FieldElement;
+#[cfg(feature = "concurrent")]
+use rayon::prelude::*;
 
-impl Polynomial{
+// Products whose shorter factor has at least this many coefficients are computed with NTTs.
+const NTT_MUL_THRESHOLD: usize = 64;
 
-    pub fn degree_of(poly: &[FieldElement]) -> usize
-    {
-        for i in (0..poly.len()).rev() {
-            if poly[i] != FieldElement::ZERO {
-                return i;
+// POLYNOMIAL
+// ================================================================================================
+
+/// Univariate polynomial over the base field, given by its coefficients, lowest degree first.
+///
+/// The coefficients are kept normalised, i.e. without trailing zeros, so the zero polynomial
+/// has no coefficients at all and two polynomials are equal iff their coefficients are.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Polynomial {
+    coefficients: Vec<FieldElement>,
+}
+
+impl Polynomial {
+
+    pub fn new(mut coefficients: Vec<FieldElement>) -> Self {
+        while coefficients.last() == Some(&FieldElement::ZERO) {
+            coefficients.pop();
+        }
+        Polynomial { coefficients }
+    }
+
+    pub fn zero() -> Self {
+        Polynomial::default()
+    }
+
+    pub fn constant(value: FieldElement) -> Self {
+        Self::new(vec![value])
+    }
+
+    /// Returns `coefficient * X^degree`.
+    pub fn monomial(degree: usize, coefficient: FieldElement) -> Self {
+        let mut coefficients = FieldElement::zeroed_vector(degree);
+        coefficients.push(coefficient);
+        Self::new(coefficients)
+    }
+
+    pub fn coefficients(&self) -> &[FieldElement] {
+        &self.coefficients
+    }
+
+    pub fn into_coefficients(self) -> Vec<FieldElement> {
+        self.coefficients
+    }
+
+    /// Degree of the polynomial, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// Leading coefficient, or zero for the zero polynomial.
+    pub fn leading_coefficient(&self) -> FieldElement {
+        self.coefficients.last().copied().unwrap_or(FieldElement::ZERO)
+    }
+
+    /// Euclidean division: returns `(quotient, remainder)` with `self = quotient * divisor +
+    /// remainder` and the remainder of smaller degree than the divisor, or `None` if the divisor
+    /// is zero.
+    pub fn divide(&self, divisor: &Self) -> Option<(Self, Self)> {
+        let divisor_degree = divisor.degree()?;
+        if self.coefficients.len() <= divisor_degree {
+            return Some((Self::zero(), self.clone()));
+        }
+
+        let leading_inverse = divisor.leading_coefficient().inverse();
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = FieldElement::zeroed_vector(remainder.len() - divisor_degree);
+        for shift in (0..quotient.len()).rev() {
+            let coefficient = remainder[shift + divisor_degree] * leading_inverse;
+            quotient[shift] = coefficient;
+            for (i, &c) in divisor.coefficients.iter().enumerate() {
+                remainder[shift + i] = remainder[shift + i] - coefficient * c;
             }
         }
-        0
+        Some((Self::new(quotient), Self::new(remainder)))
     }
 
-    pub fn is_zero(polynom: &[FieldElement]) -> bool {
-        Self::degree_of(polynom) == 0
+    pub fn evaluate(&self, point: FieldElement) -> FieldElement {
+        // Horner evaluation
+        self.coefficients.iter()
+            .rev()
+            .fold(FieldElement::ZERO, |acc, &coeff| acc * point + coeff)
     }
 
-    pub fn leading_coefficient(polynom: &[FieldElement]) -> FieldElement{
-        return polynom[Self::degree_of(polynom)];
+    pub fn evaluate_domain(&self, domain: &[FieldElement]) -> Vec<FieldElement> {
+        #[cfg(not(feature = "concurrent"))]
+        let values = domain.iter().map(|x| self.evaluate(*x)).collect();
+        #[cfg(feature = "concurrent")]
+        let values = domain.par_iter().map(|x| self.evaluate(*x)).collect();
+        values
     }
 
-    pub fn add(a: &[FieldElement], b: &[FieldElement]) -> Vec<FieldElement>
+    /// Evaluates the polynomial over the coset `{offset * omega^i : 0 <= i < length}` with a
+    /// radix-2 NTT, where `omega` is a primitive root of order `length`.
+    pub fn evaluate_coset(&self, offset: FieldElement, omega: FieldElement, length: usize)
+        -> Vec<FieldElement>
     {
-        let result_len = core::cmp::max(a.len(), b.len());
-        let mut result = Vec::with_capacity(result_len);
-        for i in 0..result_len {
-            let c1 = if i < a.len() { a[i] } else { FieldElement::ZERO };
-            let c2 = if i < b.len() { b[i] } else { FieldElement::ZERO };
-            result.push(c1 + c2);
-        }
-        result
+        assert!(length.is_power_of_two(), "coset length must be a power of two");
+        assert!(self.coefficients.len() <= length, "polynomial has more coefficients than the coset has points");
+        let mut values = self.scale(offset).into_coefficients();
+        values.resize(length, FieldElement::ZERO);
+        Self::ntt(&mut values, omega);
+        values
     }
 
-    pub fn sub(a: &[FieldElement], b: &[FieldElement]) -> Vec<FieldElement>
+    /// Inverse of `evaluate_coset`: returns the polynomial of degree less than `values.len()`
+    /// taking `values` over the coset `{offset * omega^i}`.
+    pub fn interpolate_coset(values: &[FieldElement], offset: FieldElement, omega: FieldElement)
+        -> Self
     {
-        let result_len = core::cmp::max(a.len(), b.len());
-        let mut result = Vec::with_capacity(result_len);
-        for i in 0..result_len {
-            let c1 = if i < a.len() { a[i] } else { FieldElement::ZERO };
-            let c2 = if i < b.len() { b[i] } else { FieldElement::ZERO };
-            result.push(c1 - c2);
+        assert!(values.len().is_power_of_two(), "coset length must be a power of two");
+        let mut coefficients = values.to_vec();
+        Self::ntt(&mut coefficients, omega.inverse());
+        let length_inv = FieldElement::new(values.len() as u128).inverse();
+        let offset_inv = offset.inverse();
+        let mut factor = length_inv;
+        for c in coefficients.iter_mut() {
+            *c = *c * factor;
+            factor = factor * offset_inv;
         }
-        result
+        Self::new(coefficients)
     }
 
-    pub fn mul(a: &[FieldElement], b: &[FieldElement]) -> Vec<FieldElement>
-    {
-        let result_len = a.len() + b.len() - 1;
-        let mut result = FieldElement::zeroed_vector(result_len);
-        for i in 0..a.len() {
-            for j in 0..b.len() {
-                let s = a[i] * b[j];
-                result[i + j] = result[i + j] + s;
+    /// Lagrange interpolation of the points `(domain[i], values[i])`.
+    pub fn interpolate_domain(domain: &[FieldElement], values: &[FieldElement]) -> Self {
+        assert!(domain.len() == values.len(), "number of elements in domain does not match");
+        assert!(!domain.is_empty(), "cannot interpolate between zero points");
+        let x = Self::monomial(1, FieldElement::ONE);
+        let mut acc = Self::zero();
+        for i in 0..domain.len() {
+            let mut prod = Self::constant(values[i]);
+            for j in 0..domain.len() {
+                if j == i {
+                    continue;
+                }
+                prod = prod * (&x - Self::constant(domain[j])) * (domain[i] - domain[j]).inverse();
             }
+            acc = acc + prod;
         }
-        result
+        acc
     }
 
-    pub fn division(a: &[FieldElement], b: &[FieldElement]) -> Option<(Vec<FieldElement>, Vec<FieldElement>)>
-    {
-        let mut apos = Self::degree_of(&a);
-        let mut a = a.to_vec();
+    /// Returns the monic polynomial vanishing exactly on `domain`.
+    pub fn zerofier_domain(domain: &[FieldElement]) -> Self {
+        let x = Self::monomial(1, FieldElement::ONE);
+        domain.iter().fold(Self::constant(FieldElement::ONE), |acc, &d| acc * (&x - Self::constant(d)))
+    }
 
-        let bpos = Self::degree_of(&b);
-        if (apos >= bpos) || (!b.is_empty()) {
-            return None;
+    /// Returns the polynomial `f(factor * X)`.
+    pub fn scale(&self, factor: FieldElement) -> Self {
+        let mut power = FieldElement::ONE;
+        Self::new(self.coefficients.iter()
+            .map(|&c| {
+                let scaled = power * c;
+                power = power * factor;
+                scaled
+            })
+            .collect())
+    }
+
+    /// Multiplies by evaluating both factors over a subgroup large enough to hold the product,
+    /// multiplying pointwise and interpolating, in `O(n log n)`.
+    fn mul_ntt(a: &Self, b: &Self) -> Self {
+        let length = (a.coefficients.len() + b.coefficients.len() - 1).next_power_of_two();
+        let omega = FieldElement::ZERO.primitive_nth_root(length as u128);
+        let mut values = a.evaluate_coset(FieldElement::ONE, omega, length);
+        let b_values = b.evaluate_coset(FieldElement::ONE, omega, length);
+        for (x, &y) in values.iter_mut().zip(b_values.iter()) {
+            *x = *x * y;
         }
+        Self::interpolate_coset(&values, FieldElement::ONE, omega)
+    }
+
+    /// Checks whether the points `(domain[i], values[i])` lie on a line.
+    pub fn test_colinearity(domain: &[FieldElement], values: &[FieldElement]) -> bool {
+        Self::interpolate_domain(domain, values).degree().is_none_or(|degree| degree <= 1)
+    }
+
+    // NTT
+    // --------------------------------------------------------------------------------------------
 
-        let mut result = FieldElement::zeroed_vector(apos - bpos + 1);
-        for _ in 0..result.len() {
-            if apos < bpos {
-                break;
+    /// Replaces `values` by their evaluations `sum_j values[j] * omega^(i * j)`, using an
+    /// iterative Cooley-Tukey transform. With the `concurrent` feature the butterflies of every
+    /// stage are spread over threads; they are independent, so the result does not change.
+    fn ntt(values: &mut [FieldElement], omega: FieldElement) {
+        let n = values.len();
+        let log_n = n.trailing_zeros();
+        for i in 0..n {
+            let j = i.reverse_bits() >> (usize::BITS - log_n) as usize;
+            if i < j {
+                values.swap(i, j);
             }
-            let coefficient = Self::leading_coefficient(&a) / Self::leading_coefficient(&b);
-            let shift = Self::degree_of(&a) - Self::degree_of(&b);
-            let mut subtractee = FieldElement::zeroed_vector(shift);
-            subtractee.push(coefficient);
-            subtractee = Self::mul(&subtractee, &b);
-            result[shift] = coefficient;
-            a = Self::sub(&a, &subtractee);
-            apos = Self::degree_of(&a);
         }
 
-        Some((result, a))
+        let mut half = 1;
+        while half < n {
+            let root = omega ^ (n / (2 * half)) as u128;
+            let mut twiddles = Vec::with_capacity(half);
+            let mut twiddle = FieldElement::ONE;
+            for _ in 0..half {
+                twiddles.push(twiddle);
+                twiddle = twiddle * root;
+            }
+
+            #[cfg(not(feature = "concurrent"))]
+            values.chunks_mut(2 * half).for_each(|chunk| Self::butterflies(chunk, &twiddles));
+            #[cfg(feature = "concurrent")]
+            values.par_chunks_mut(2 * half).for_each(|chunk| Self::butterflies(chunk, &twiddles));
+
+            half *= 2;
+        }
     }
 
-    pub fn div(a: &[FieldElement], b: &[FieldElement]) 
-        -> Option<Vec<FieldElement>>
-    {
-        let (quo, rem) = match Self::division(a, b){
-            Some((quo, rem)) => (quo, rem),
-            None => {return None;}
-        };
-        assert!(rem.len() == 0, "remainder is not zero");
-        return Some(quo);
+    fn butterflies(chunk: &mut [FieldElement], twiddles: &[FieldElement]) {
+        let (low, high) = chunk.split_at_mut(twiddles.len());
+        for ((u, v), &twiddle) in low.iter_mut().zip(high.iter_mut()).zip(twiddles) {
+            let t = *v * twiddle;
+            *v = *u - t;
+            *u = *u + t;
+        }
     }
+}
 
-    pub fn module(a: &[FieldElement], b: &[FieldElement]) 
-        -> Option<Vec<FieldElement>>
-    {
-        let (_, rem) = match Self::division(a, b){
-            Some((_quo, rem)) => (_quo, rem),
-            None => {return None;}
-        };
-        return Some(rem);
+// OPERATORS
+// ================================================================================================
+
+impl From<Vec<FieldElement>> for Polynomial {
+    fn from(coefficients: Vec<FieldElement>) -> Self {
+        Polynomial::new(coefficients)
+    }
+}
+
+impl Add<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, rhs: &Polynomial) -> Polynomial {
+        let (long, short) = if self.coefficients.len() >= rhs.coefficients.len() { (self, rhs) } else { (rhs, self) };
+        let mut coefficients = long.coefficients.clone();
+        for (c, &d) in coefficients.iter_mut().zip(short.coefficients.iter()) {
+            *c = *c + d;
+        }
+        Polynomial::new(coefficients)
+    }
+}
+
+impl Sub<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, rhs: &Polynomial) -> Polynomial {
+        self + &(-rhs)
     }
+}
+
+impl Mul<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
 
-    pub fn xor(a: &[FieldElement], exponent: u128) -> Vec<FieldElement> {
-        if Self::is_zero(a){
-            return Vec::new();
+    fn mul(self, rhs: &Polynomial) -> Polynomial {
+        if self.is_zero() || rhs.is_zero() {
+            return Polynomial::zero();
         }
-        if exponent == 0 {
-            return vec![FieldElement::ONE];
+        if self.coefficients.len().min(rhs.coefficients.len()) >= NTT_MUL_THRESHOLD {
+            return Polynomial::mul_ntt(self, rhs);
         }
-        let mut acc = vec![FieldElement::ONE];
-        for i in 128..=0 {
-            acc = Self::mul(&acc, &acc);
-            if (1 << i) & exponent != 0 {
-                acc = Self::mul(&acc, a);
+        let mut coefficients = FieldElement::zeroed_vector(self.coefficients.len() + rhs.coefficients.len() - 1);
+        for (i, &a) in self.coefficients.iter().enumerate() {
+            for (j, &b) in rhs.coefficients.iter().enumerate() {
+                coefficients[i + j] = coefficients[i + j] + a * b;
             }
         }
-        acc
+        Polynomial::new(coefficients)
     }
+}
 
-    pub fn evaluate(a: &[FieldElement], point: FieldElement) -> FieldElement {
-        // let mut xi = FieldElement::ONE;
-        // let mut value = FieldElement::ZERO;
-        // for c in a.iter(){
-        //     value = value + *c * xi;
-        //     xi = xi * point;
-        // }
-        // value
-        // Horner evaluation
-        a.iter()
-            .rev()
-            .fold(FieldElement::ZERO, |acc, &coeff| acc * point + FieldElement::from(coeff))
-    }
-
-    pub fn evaluate_domain(a: &[FieldElement], 
-            domain: &[FieldElement]) -> Vec<FieldElement> {
-        
-        domain.iter().map(|x| Self::evaluate(a, *x)).collect()
-    }
-
-    pub fn interpolate_domain(domain: &[FieldElement], 
-            values: &[FieldElement]) -> Vec<FieldElement> {
-        
-        let domain_len = domain.len();
-        assert!(domain_len == values.len(), "number of elements in domain does not match");
-        assert!(domain_len > 0, "cannot interpolate between zero points");
-        let x = vec![FieldElement::ZERO, FieldElement::ONE];
-        let mut res = vec![];
-        for i in 0..domain_len {
-            let mut prod = vec![values[i]];
-            for j in 0..domain_len {
-                if j == i{
-                    continue;
-                }
-                let first = Self::sub(&x, &[domain[j]]);
-                let second = vec![(domain[i] - domain[j]).inverse()];
-                prod = Self::mul(&prod, &Self::mul(&first, &second));
-            }
-            res = Self::add(&res, &prod);
-        }
-        res
+impl Div<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    /// Quotient of the Euclidean division; panics if the divisor is zero.
+    fn div(self, rhs: &Polynomial) -> Polynomial {
+        self.divide(rhs).expect("cannot divide by the zero polynomial").0
+    }
+}
+
+impl Rem<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    /// Remainder of the Euclidean division; panics if the divisor is zero.
+    fn rem(self, rhs: &Polynomial) -> Polynomial {
+        self.divide(rhs).expect("cannot divide by the zero polynomial").1
+    }
+}
+
+forward_binary_op!(Polynomial, Add, add);
+forward_binary_op!(Polynomial, Sub, sub);
+forward_binary_op!(Polynomial, Mul, mul);
+forward_binary_op!(Polynomial, Div, div);
+forward_binary_op!(Polynomial, Rem, rem);
+
+impl Neg for &Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        Polynomial { coefficients: self.coefficients.iter().map(|&c| -c).collect() }
+    }
+}
+
+impl Neg for Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        -&self
+    }
+}
+
+impl Mul<FieldElement> for &Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, rhs: FieldElement) -> Polynomial {
+        Polynomial::new(self.coefficients.iter().map(|&c| c * rhs).collect())
+    }
+}
+
+impl Mul<FieldElement> for Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, rhs: FieldElement) -> Polynomial {
+        &self * rhs
     }
+}
 
-    pub fn zerofier_domain(domain: &[FieldElement]) -> Vec<FieldElement> {
-        let x = vec![FieldElement::ZERO, FieldElement::ONE];
-        let mut acc = vec![FieldElement::ONE];
-        for d in domain{
-            acc = Self::mul(&acc, &Self::sub(&x, &[*d]));
+impl BitXor<u128> for &Polynomial {
+    type Output = Polynomial;
+
+    /// Raises the polynomial to the given power using square-and-multiply.
+    fn bitxor(self, exponent: u128) -> Polynomial {
+        let mut acc = Polynomial::constant(FieldElement::ONE);
+        for i in (0..(128 - exponent.leading_zeros())).rev() {
+            acc = &acc * &acc;
+            if (exponent >> i) & 1 == 1 {
+                acc = acc * self;
+            }
         }
         acc
     }
+}
 
-    pub fn scale(x: &[FieldElement], factor: FieldElement) -> Vec<FieldElement> {
-        let mut polynom: Vec<FieldElement> = Vec::new();
-        for i in 0..x.len() {
-            polynom.push((factor ^ (i as u128)) * x[i]);
-        }
-        polynom
+impl BitXor<u128> for Polynomial {
+    type Output = Polynomial;
+
+    fn bitxor(self, exponent: u128) -> Polynomial {
+        &self ^ exponent
     }
+}
 
-    pub fn test_colinearity(domain: &[FieldElement], values: &[FieldElement]) -> bool {
-        Self::degree_of(&Self::interpolate_domain(domain, values)) == 1
+impl fmt::Display for Polynomial {
+    /// Writes the polynomial in `x`, highest degree first, e.g. `x^2 + 3*x + 5`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut first = true;
+        for (i, c) in self.coefficients.iter().enumerate().rev() {
+            if c.is_zero() {
+                continue;
+            }
+            if !first {
+                write!(f, " + ")?;
+            }
+            first = false;
+            match (i, c.as_int()) {
+                (0, c) => write!(f, "{}", c)?,
+                (_, 1) => {}
+                (_, c) => write!(f, "{}*", c)?,
+            }
+            match i {
+                0 => {}
+                1 => write!(f, "x")?,
+                _ => write!(f, "x^{}", i)?,
+            }
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+}
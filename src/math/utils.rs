@@ -1,18 +1,5 @@
 use super::{M};
 
-pub fn xgcd(x: u128, y: u128) -> (u128, u128, u128) {
-    let (mut old_r, mut r) = (x, y);
-    let (mut old_s, mut s) = (1, 0);
-    let (mut old_t, mut t) = (0, 1);
-    while r != 0 {
-        let quotient = old_r / r;
-        (old_r, r) = (r, old_r - quotient * r);
-        (old_s, s) = (s, old_s - quotient * s);
-        (old_t, t) = (t, old_t - quotient * t);
-    }
-    (old_s, old_t, old_r)
-}
-
 pub fn add(a: u128, b: u128) -> u128 {
     let z = M - b;
     if a < z {
@@ -31,42 +18,12 @@ pub fn sub(a: u128, b: u128) -> u128 {
 }
 
 pub fn mul(a: u128, b: u128) -> u128 {
-    let (x0, x1, x2) = mul_128x64(a, (b >> 64) as u64); // x = a * b_hi
-    let (mut x0, mut x1, x2) = mul_reduce(x0, x1, x2); // x = x - (x >> 128) * m
-    if x2 == 1 {
-        // if there was an overflow beyond 128 bits, subtract
-        // modulus from the result to make sure it fits into
-        // 128 bits; this can potentially be removed in favor
-        // of checking overflow later
-        let (t0, t1) = sub_modulus(x0, x1); // x = x - m
-        x0 = t0;
-        x1 = t1;
-    }
-
-    let (y0, y1, y2) = mul_128x64(a, b as u64); // y = a * b_lo
-
-    let (mut y1, carry) = add64_with_carry(y1, x0, 0); // y = y + (x << 64)
-    let (mut y2, y3) = add64_with_carry(y2, x1, carry);
-    if y3 == 1 {
-        // if there was an overflow beyond 192 bits, subtract
-        // modulus * 2^64 from the result to make sure it fits
-        // into 192 bits; this can potentially replace the
-        // previous overflow check (but needs to be proven)
-        let (t0, t1) = sub_modulus(y1, y2); // y = y - (m << 64)
-        y1 = t0;
-        y2 = t1;
-    }
-
-    let (mut z0, mut z1, z2) = mul_reduce(y0, y1, y2); // z = y - (y >> 128) * m
-
-    // make sure z is smaller than m
-    if z2 == 1 || (z1 == (M >> 64) as u64 && z0 >= (M as u64)) {
-        let (t0, t1) = sub_modulus(z0, z1); // z = z - m
-        z0 = t0;
-        z1 = t1;
-    }
-
-    ((z1 as u128) << 64) + (z0 as u128)
+    // the product is computed in Montgomery form: redc(a * b) = a * b / 2^128, so one more
+    // reduction against 2^256 mod m is needed to bring the result back to canonical form
+    let (z_lo, z_hi) = mul_128x128(a, b); // z = a * b
+    let z = mont_reduce(z_lo, z_hi); // z = a * b / 2^128
+    let (z_lo, z_hi) = mul_128x128(z, R2); // z = z * 2^256
+    mont_reduce(z_lo, z_hi) // z = a * b
 }
 
 pub fn inv(x: u128) -> u128 {
@@ -164,34 +121,41 @@ pub fn inv(x: u128) -> u128 {
     a
 }
 
-#[inline]
-fn mul_128x64(a: u128, b: u64) -> (u64, u64, u64) {
-    let z_lo = ((a as u64) as u128) * (b as u128);
-    let z_hi = (a >> 64) * (b as u128);
-    let z_hi = z_hi + (z_lo >> 64);
-    (z_lo as u64, z_hi as u64, (z_hi >> 64) as u64)
-}
+// 2^256 mod m
+const R2: u128 = 227239200783092534449076146062029718070;
 
-#[inline]
-fn mul_reduce(z0: u64, z1: u64, z2: u64) -> (u64, u64, u64) {
-    let (q0, q1, q2) = mul_by_modulus(z2);
-    let (z0, z1, z2) = sub_192x192(z0, z1, z2, q0, q1, q2);
-    (z0, z1, z2)
-}
+// -m^{-1} mod 2^128
+const M_INV_NEG: u128 = 270497897142230380135924736767050121215;
 
 #[inline]
-fn mul_by_modulus(a: u64) -> (u64, u64, u64) {
-    let a_lo = (a as u128).wrapping_mul(M);
-    let a_hi = if a == 0 { 0 } else { a - 1 };
-    (a_lo as u64, (a_lo >> 64) as u64, a_hi)
+fn mul_128x128(a: u128, b: u128) -> (u128, u128) {
+    let (a0, a1) = (a as u64 as u128, a >> 64);
+    let (b0, b1) = (b as u64 as u128, b >> 64);
+
+    let lo = a0 * b0;
+    let (mid, mid_carry) = (a0 * b1).overflowing_add(a1 * b0);
+    let hi = a1 * b1 + ((mid_carry as u128) << 64);
+
+    let (lo, carry) = lo.overflowing_add(mid << 64);
+    let hi = hi + (mid >> 64) + (carry as u128);
+    (lo, hi)
 }
 
 #[inline]
-fn sub_modulus(a_lo: u64, a_hi: u64) -> (u64, u64) {
-    let mut z = 0u128.wrapping_sub(M);
-    z = z.wrapping_add(a_lo as u128);
-    z = z.wrapping_add((a_hi as u128) << 64);
-    (z as u64, (z >> 64) as u64)
+fn mont_reduce(z_lo: u128, z_hi: u128) -> u128 {
+    // q is chosen so that z + q * m is divisible by 2^128
+    let q = z_lo.wrapping_mul(M_INV_NEG);
+    let (q_lo, q_hi) = mul_128x128(q, M);
+    let (_, carry) = z_lo.overflowing_add(q_lo);
+
+    // (z + q * m) / 2^128 is smaller than 2m, which may not fit into 128 bits
+    let (r, overflow_1) = z_hi.overflowing_add(q_hi);
+    let (r, overflow_2) = r.overflowing_add(carry as u128);
+    if overflow_1 || overflow_2 || r >= M {
+        r.wrapping_sub(M)
+    } else {
+        r
+    }
 }
 
 #[inline]
@@ -209,9 +173,3 @@ fn add_192x192(a0: u64, a1: u64, a2: u64, b0: u64, b1: u64, b2: u64) -> (u64, u6
     let z2 = (a2 as u128) + (b2 as u128) + (z1 >> 64);
     (z0 as u64, z1 as u64, z2 as u64)
 }
-
-#[inline]
-const fn add64_with_carry(a: u64, b: u64, carry: u64) -> (u64, u64) {
-    let ret = (a as u128) + (b as u128) + (carry as u128);
-    (ret as u64, (ret >> 64) as u64)
-}
@@ -0,0 +1,209 @@
+use super::field_element::FieldElement;
+use super::polynomial::Polynomial;
+
+// Number of roots below which a product tree multiplies linear factors one by one.
+const PRODUCT_TREE_LEAF_SIZE: usize = 32;
+
+// ZEROFIER
+// ================================================================================================
+
+/// Vanishing polynomial of a multiplicative subgroup with some of its elements left out.
+///
+/// For a subgroup of order `T` generated by `omega` and a set of excluded rows `E`, the zerofier
+/// is `(X^T - 1) / prod_{i in E} (X - omega^i)`. It is kept in this sparse form rather than as a
+/// product of `T - |E|` linear factors, so evaluating it at a single point costs `O(log T + |E|)`
+/// field operations.
+///
+/// When most rows are excluded, as for a trace occupying a prefix of a much larger subgroup, the
+/// product of the kept factors is the cheaper form: a point evaluation then costs
+/// `O(log T + T - |E|)`, linear in the number of kept rows rather than logarithmic in `T`. Once
+/// more than `log2(T)` rows are excluded, coset evaluations build the dense polynomial with a
+/// product tree and evaluate it with an NTT.
+///
+/// The transition zerofier of a trace of length `T` is the zerofier excluding the last row; a
+/// constraint spanning `k + 1` consecutive rows excludes the last `k` rows instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Zerofier {
+    pub omega: FieldElement,
+    pub order: usize,
+    excluded_rows: Vec<usize>,
+    excluded_points: Vec<FieldElement>,
+}
+
+impl Zerofier {
+
+    /// Returns the vanishing polynomial `X^order - 1` of the subgroup generated by `omega`.
+    pub fn new(omega: FieldElement, order: usize) -> Self {
+        Self::excluding(omega, order, &[])
+    }
+
+    /// Returns the zerofier of the subgroup generated by `omega` which does not vanish on
+    /// `omega^i` for any `i` in `rows`.
+    pub fn excluding(omega: FieldElement, order: usize, rows: &[usize]) -> Self {
+        assert!(order > 0 && order & (order - 1) == 0, "order must be a power of two");
+        assert!(omega ^ (order as u128) == FieldElement::ONE, "omega is not of the given order");
+        assert!(order == 1 || omega ^ (order as u128 / 2) != FieldElement::ONE,
+            "omega is not a primitive root of the given order");
+
+        let mut excluded_rows = rows.to_vec();
+        excluded_rows.sort_unstable();
+        excluded_rows.dedup();
+        assert!(excluded_rows.last().is_none_or(|&row| row < order), "excluded row out of range");
+
+        let excluded_points = excluded_rows.iter().map(|&row| omega ^ (row as u128)).collect();
+        Zerofier {
+            omega,
+            order,
+            excluded_rows,
+            excluded_points,
+        }
+    }
+
+    /// Returns the zerofier for transition constraints over a trace of length `order`, i.e.
+    /// the vanishing polynomial of all rows but the last one.
+    pub fn transition(omega: FieldElement, order: usize) -> Self {
+        Self::excluding_last(omega, order, 1)
+    }
+
+    /// Returns the vanishing polynomial of all rows but the last `num_rows` ones.
+    pub fn excluding_last(omega: FieldElement, order: usize, num_rows: usize) -> Self {
+        assert!(num_rows <= order, "cannot exclude more rows than the subgroup contains");
+        let rows = ((order - num_rows)..order).collect::<Vec<usize>>();
+        Self::excluding(omega, order, &rows)
+    }
+
+    pub fn excluded_rows(&self) -> &[usize] {
+        &self.excluded_rows
+    }
+
+    pub fn degree(&self) -> usize {
+        self.order - self.excluded_rows.len()
+    }
+
+    /// Evaluates the zerofier at `point` in `O(log T + min(|E|, T - |E|))` field operations, from
+    /// the sparse form or from the kept factors, whichever is smaller.
+    pub fn evaluate(&self, point: FieldElement) -> FieldElement {
+        if self.excluded_rows.len() > self.degree() {
+            return self.kept_roots().fold(FieldElement::ONE, |acc, root| acc * (point - root));
+        }
+        let numerator = (point ^ (self.order as u128)) - FieldElement::ONE;
+        if !numerator.is_zero() {
+            let denominator = self.excluded_points.iter()
+                .fold(FieldElement::ONE, |acc, &e| acc * (point - e));
+            return numerator / denominator;
+        }
+        self.evaluate_in_subgroup(point)
+    }
+
+    /// Evaluates the zerofier over the coset `{offset * omega^i : 0 <= i < length}`.
+    ///
+    /// With few excluded rows, numerators are computed incrementally and all denominators are
+    /// inverted in one batch, so the cost is `O(length * |E|)` multiplications and a single
+    /// inversion. Otherwise, when `omega` is a primitive root of order `length`, the dense
+    /// polynomial is evaluated with an NTT in `O(length * log(length))`.
+    pub fn evaluate_coset(&self, offset: FieldElement, omega: FieldElement, length: usize)
+        -> Vec<FieldElement>
+    {
+        let is_subgroup = length.is_power_of_two()
+            && omega ^ (length as u128) == FieldElement::ONE
+            && (length == 1 || omega ^ (length as u128 / 2) != FieldElement::ONE);
+        if !self.is_sparse() && is_subgroup && self.degree() < length {
+            return self.polynomial().evaluate_coset(offset, omega, length);
+        }
+
+        let order = self.order as u128;
+        let step = omega ^ order;
+        let mut numerators = Vec::with_capacity(length);
+        let mut points = Vec::with_capacity(length);
+        let mut x = offset;
+        let mut x_pow = offset ^ order;
+        for _ in 0..length {
+            numerators.push(x_pow - FieldElement::ONE);
+            points.push(x);
+            x = x * omega;
+            x_pow = x_pow * step;
+        }
+
+        let denominators = points.iter()
+            .map(|&x| self.excluded_points.iter().fold(FieldElement::ONE, |acc, &e| acc * (x - e)))
+            .collect::<Vec<FieldElement>>();
+        let inv_denominators = FieldElement::batch_inverse(&denominators);
+
+        (0..length)
+            .map(|i| if numerators[i].is_zero() {
+                self.evaluate_in_subgroup(points[i])
+            } else {
+                numerators[i] * inv_denominators[i]
+            })
+            .collect()
+    }
+
+    /// Returns the zerofier as a dense polynomial, by synthetic division of `X^T - 1` by the
+    /// excluded factors if there are few of them, and as the product of the kept factors
+    /// otherwise.
+    pub fn polynomial(&self) -> Polynomial {
+        if !self.is_sparse() {
+            return Self::product_tree(&self.kept_roots().collect::<Vec<FieldElement>>());
+        }
+
+        let mut coefficients = FieldElement::zeroed_vector(self.order + 1);
+        coefficients[0] = -FieldElement::ONE;
+        coefficients[self.order] = FieldElement::ONE;
+
+        // every excluded point is a root, so synthetic division by (X - e) is exact
+        for &e in self.excluded_points.iter() {
+            let degree = coefficients.len() - 1;
+            let mut quotient = FieldElement::zeroed_vector(degree);
+            quotient[degree - 1] = coefficients[degree];
+            for i in (1..degree).rev() {
+                quotient[i - 1] = coefficients[i] + e * quotient[i];
+            }
+            coefficients = quotient;
+        }
+        Polynomial::from(coefficients)
+    }
+
+    // HELPER FUNCTIONS
+    // --------------------------------------------------------------------------------------------
+
+    // whether the sparse form is the cheaper one, i.e. at most `log2(T)` rows are excluded
+    fn is_sparse(&self) -> bool {
+        self.excluded_rows.len() <= self.order.trailing_zeros() as usize
+    }
+
+    // `omega^i` for every kept row `i`, walking the runs between excluded rows
+    fn kept_roots(&self) -> impl Iterator<Item = FieldElement> + '_ {
+        let starts = std::iter::once(0).chain(self.excluded_rows.iter().map(|&row| row + 1));
+        let ends = self.excluded_rows.iter().copied().chain(std::iter::once(self.order));
+        starts.zip(ends).flat_map(move |(start, end)| {
+            let mut root = self.omega ^ (start as u128);
+            (start..end).map(move |_| {
+                let current = root;
+                root = root * self.omega;
+                current
+            })
+        })
+    }
+
+    // product of `X - root` over all roots, with the halves multiplied by NTT once they are large
+    fn product_tree(roots: &[FieldElement]) -> Polynomial {
+        if roots.len() <= PRODUCT_TREE_LEAF_SIZE {
+            return Polynomial::zerofier_domain(roots);
+        }
+        let (left, right) = roots.split_at(roots.len() / 2);
+        &Self::product_tree(left) * &Self::product_tree(right)
+    }
+
+    /// Evaluates the zerofier at a point of the subgroup, where the sparse form is `0 / 0` for
+    /// excluded rows. There the value is `(T / x) / prod_{e != x} (x - e)`, since the derivative
+    /// of `X^T - 1` at a root `x` equals `T * x^(T - 1) = T / x`.
+    fn evaluate_in_subgroup(&self, point: FieldElement) -> FieldElement {
+        if !self.excluded_points.contains(&point) {
+            return FieldElement::ZERO;
+        }
+        let denominator = self.excluded_points.iter()
+            .filter(|&&e| e != point)
+            .fold(point, |acc, &e| acc * (point - e));
+        FieldElement::new(self.order as u128) / denominator
+    }
+}
@@ -1,63 +1,736 @@
+#[cfg(unix)]
+use std::{fs::{File, OpenOptions}, io, os::unix::fs::FileExt, path::Path};
 use blake2::{Blake2s256, Digest};
+use rand_core::RngCore;
+#[cfg(feature = "concurrent")]
+use rayon::prelude::*;
+use crate::math::FieldElement;
+use crate::proof::Opening;
+
+/// Number of random bytes prepended to every leaf of a `SaltedMerkleTree`.
+pub const SALT_BYTES: usize = 16;
+
+// Number of parent nodes hashed at a time when building a tree in a `NodeStorage`.
+const BUILD_CHUNK_SIZE: usize = 1 << 14;
+
+// Prefixes of the hash inputs of leaves and internal nodes. Without them a data element made of
+// two concatenated child hashes would hash to their parent and could be opened as a leaf at a
+// shallower depth.
+const LEAF_TAG: u8 = 0;
+const NODE_TAG: u8 = 1;
 
 pub struct Merkle {}
 
 impl Merkle {
 
-    fn commit_(leafs: &[u8]) -> [u8; 32] {
-        assert!(leafs.len() & (leafs.len() - 1) == 0, "length must be power of two");
-        if leafs.len() == 1{
-            return leafs.try_into().unwrap();
+    pub(crate) fn hash_children(children: &[[u8; 32]]) -> [u8; 32] {
+        Blake2s256::new().chain_update([NODE_TAG]).chain_update(children.concat()).finalize().into()
+    }
+
+    // the depth of the path is fixed by `num_leafs` and the length of the cap, so a path can
+    // neither stop at an inner node nor climb past the cap
+    fn verify_(num_leafs: usize, arity: usize, cap: &[[u8; 32]], index: usize, path: &[[u8; 32]],
+        leaf: [u8; 32]) -> bool
+    {
+        if !num_leafs.is_power_of_two() || index >= num_leafs {
+            return false;
+        }
+        let mut fan_outs = MerkleTree::fan_outs(num_leafs, arity);
+        let mut cap_length = 1;
+        while cap_length < cap.len() {
+            match fan_outs.pop() {
+                Some(fan_out) => cap_length *= fan_out,
+                None => return false,
+            }
+        }
+        if cap_length != cap.len() || path.len() != fan_outs.iter().map(|fan_out| fan_out - 1).sum() {
+            return false;
         }
-        else {
-            return Blake2s256::digest(&Self::concat(&Self::commit_(&leafs[0..leafs.len()/2]), &Self::commit_(&leafs[leafs.len()/2..]))).into()
+
+        let mut node = leaf;
+        let mut index = index;
+        let mut siblings = path;
+        for fan_out in fan_outs {
+            let position = index % fan_out;
+            let mut children = Vec::with_capacity(fan_out);
+            children.extend_from_slice(&siblings[..position]);
+            children.push(node);
+            children.extend_from_slice(&siblings[position..fan_out - 1]);
+            node = Self::hash_children(&children);
+            siblings = &siblings[fan_out - 1..];
+            index /= fan_out;
         }
+        cap[index] == node
     }
 
-    fn concat(left: &[u8], right: &[u8]) -> Vec<u8> {
-        let mut concatenated: Vec<u8> = (*left).into();
-        let mut right_node_clone: Vec<u8> = (*right).into();
-        concatenated.append(&mut right_node_clone);
-        concatenated
+    // `fan_outs` are the fan-outs of the levels of the tree, bottom level first
+    fn verify_many_(root: [u8; 32], indices: &[usize], leafs: &[[u8; 32]], proof: &[[u8; 32]],
+        fan_outs: Vec<usize>) -> bool
+    {
+        if indices.is_empty() || indices.len() != leafs.len() {
+            return false;
+        }
+        let mut known = indices.iter().copied().zip(leafs.iter().copied()).collect::<Vec<_>>();
+        known.sort_unstable_by_key(|&(index, _)| index);
+        known.dedup();
+        if known.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return false;
+        }
+
+        let mut proof = proof.iter();
+        for fan_out in fan_outs {
+            // every parent of a known node is computed from its children, taking the ones that
+            // are not known from the proof
+            let mut i = 0;
+            let mut parents = Vec::with_capacity(known.len());
+            while i < known.len() {
+                let parent = known[i].0 / fan_out;
+                let mut children = Vec::with_capacity(fan_out);
+                for child in (parent * fan_out)..((parent + 1) * fan_out) {
+                    if i < known.len() && known[i].0 == child {
+                        children.push(known[i].1);
+                        i += 1;
+                    } else {
+                        match proof.next() {
+                            Some(&node) => children.push(node),
+                            None => return false,
+                        }
+                    }
+                }
+                parents.push((parent, Self::hash_children(&children)));
+            }
+            known = parents;
+        }
+        proof.len() == 0 && known.len() == 1 && known[0] == (0, root)
+    }
+
+    pub fn commit(data_array: &[Vec<u8>]) -> [u8; 32] {
+        MerkleTree::new(data_array).root()
+    }
+
+    pub fn open(index: usize, data_array: &[Vec<u8>]) -> Vec<[u8; 32]> {
+        MerkleTree::new(data_array).prove(index)
     }
 
-    fn open_(index: usize, leafs: &[u8]) -> Vec<u8> {
-        assert!(leafs.len() & (leafs.len() - 1) == 0, "must be power of two");
-        assert!((0 <= index) && (index < leafs.len()), "cannot open invalid index");
-        if leafs.len() == 2 {
-            return vec![leafs[1-index]];
+    /// Verifies a path in a binary tree over `num_leafs` leaves.
+    pub fn verify(num_leafs: usize, root: [u8; 32], index: usize, path: &[[u8; 32]], data_element: &[u8]) -> bool {
+        Self::verify_(num_leafs, 2, &[root], index, path, Self::hash_leaf(data_element))
+    }
+
+    /// Commits to `data_array` with the `2^cap_height` nodes of the layer `cap_height` levels
+    /// below the root instead of the root itself; a cap height of 0 gives `[root]`.
+    pub fn commit_cap(data_array: &[Vec<u8>], cap_height: usize) -> Vec<[u8; 32]> {
+        MerkleTree::new(data_array).cap(cap_height)
+    }
+
+    /// Returns the authentication path of `index` up to the cap, which is `cap_height` nodes
+    /// shorter than the path to the root.
+    pub fn open_to_cap(index: usize, data_array: &[Vec<u8>], cap_height: usize) -> Vec<[u8; 32]> {
+        MerkleTree::new(data_array).prove_to_cap(index, cap_height)
+    }
+
+    /// Verifies a path against a cap: the high bits of `index` above the path length select the
+    /// cap node the path must lead to.
+    pub fn verify_with_cap(num_leafs: usize, cap: &[[u8; 32]], index: usize, path: &[[u8; 32]],
+        data_element: &[u8]) -> bool
+    {
+        Self::verify_(num_leafs, 2, cap, index, path, Self::hash_leaf(data_element))
+    }
+
+    /// Verifies a path in a tree of the given arity against a cap, or against `&[root]`. The
+    /// path carries `arity - 1` siblings per level, except for a smaller level right below the
+    /// root when the number of leaves is not a power of the arity; see `MerkleTree`.
+    pub fn verify_with_arity(arity: usize, num_leafs: usize, cap: &[[u8; 32]], index: usize,
+        path: &[[u8; 32]], data_element: &[u8]) -> bool
+    {
+        assert!(arity >= 2 && arity.is_power_of_two(), "arity must be a power of two");
+        Self::verify_(num_leafs, arity, cap, index, path, Self::hash_leaf(data_element))
+    }
+
+    /// Returns the sibling nodes needed to authenticate all `indices` at once; see
+    /// `MerkleTree::prove_batch`.
+    pub fn open_many(indices: &[usize], data_array: &[Vec<u8>]) -> Vec<[u8; 32]> {
+        MerkleTree::new(data_array).prove_batch(indices)
+    }
+
+    /// Verifies a batch proof of a binary tree over `num_leafs` leaves.
+    pub fn verify_many(num_leafs: usize, root: [u8; 32], indices: &[usize], data_elements: &[Vec<u8>],
+        proof: &[[u8; 32]]) -> bool
+    {
+        Self::verify_many_with_arity(2, num_leafs, root, indices, data_elements, proof)
+    }
+
+    /// Verifies a batch proof of a tree of the given arity over `num_leafs` leaves.
+    pub fn verify_many_with_arity(arity: usize, num_leafs: usize, root: [u8; 32], indices: &[usize],
+        data_elements: &[Vec<u8>], proof: &[[u8; 32]]) -> bool
+    {
+        Self::verify_many_with_arity_(arity, num_leafs, root, indices, &Self::hash_leafs(data_elements), proof)
+    }
+
+    fn verify_many_with_arity_(arity: usize, num_leafs: usize, root: [u8; 32], indices: &[usize],
+        leafs: &[[u8; 32]], proof: &[[u8; 32]]) -> bool
+    {
+        if !num_leafs.is_power_of_two() || indices.iter().any(|&index| index >= num_leafs) {
+            return false;
         }
-        if index < leafs.len() / 2 {
-            let mut recur_vec = Self::open_(index, &leafs[..leafs.len()/2]);
-            recur_vec.extend_from_slice(&Self::commit_(&leafs[leafs.len()/2..]));
-            return recur_vec;
+        Self::verify_many_(root, indices, leafs, proof, MerkleTree::fan_outs(num_leafs, arity))
+    }
+
+    /// Leaf hash of a row of field elements: the hash of the concatenated bytes of the elements,
+    /// so that `Merkle::verify` accepts the row's bytes as data element.
+    pub fn hash_row(row: &[FieldElement]) -> [u8; 32] {
+        Self::hash_leaf(FieldElement::elements_as_bytes(row))
+    }
+
+    /// Leaf hash of a data element, as computed by `MerkleTree::new`. Leaves and internal nodes
+    /// are hashed with different prefixes, so no leaf can pass for an internal node.
+    pub fn hash_leaf(data_element: &[u8]) -> [u8; 32] {
+        Blake2s256::new().chain_update([LEAF_TAG]).chain_update(data_element).finalize().into()
+    }
+
+    /// Leaf hash of a `SaltedMerkleTree`: the hash of the salt followed by the data.
+    pub fn hash_salted(salt: &[u8; SALT_BYTES], data_element: &[u8]) -> [u8; 32] {
+        Blake2s256::new().chain_update([LEAF_TAG]).chain_update(salt).chain_update(data_element)
+            .finalize().into()
+    }
+
+    fn hash_leafs(data_array: &[Vec<u8>]) -> Vec<[u8; 32]> {
+        #[cfg(not(feature = "concurrent"))]
+        let leafs = data_array.iter().map(|x| Self::hash_leaf(x)).collect();
+        #[cfg(feature = "concurrent")]
+        let leafs = data_array.par_iter().map(|x| Self::hash_leaf(x)).collect();
+        leafs
+    }
+}
+
+// NODE STORAGE
+// ================================================================================================
+
+/// Storage of the flat node array of a `MerkleTree`, addressed by node position.
+pub trait NodeStorage {
+    /// Number of node slots, i.e. twice the number of leaves.
+    fn num_nodes(&self) -> usize;
+
+    /// Reads `nodes.len()` consecutive nodes starting at `position`.
+    fn read(&self, position: usize, nodes: &mut [[u8; 32]]);
+
+    /// Writes consecutive nodes starting at `position`.
+    fn write(&mut self, position: usize, nodes: &[[u8; 32]]);
+}
+
+impl NodeStorage for Vec<[u8; 32]> {
+    fn num_nodes(&self) -> usize {
+        self.len()
+    }
+
+    fn read(&self, position: usize, nodes: &mut [[u8; 32]]) {
+        nodes.copy_from_slice(&self[position..(position + nodes.len())]);
+    }
+
+    fn write(&mut self, position: usize, nodes: &[[u8; 32]]) {
+        self[position..(position + nodes.len())].copy_from_slice(nodes);
+    }
+}
+
+/// Node storage in a file, for trees too large to be held in memory: the nodes are laid out as in
+/// memory, 32 bytes each, and read and written in chunks with positioned I/O. I/O errors after
+/// the file was created or opened panic, as the tree has no way to report them.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct FileStorage {
+    file: File,
+    num_nodes: usize,
+}
+
+#[cfg(unix)]
+impl FileStorage {
+
+    /// Creates the file at `path`, or truncates it if it exists, with room for a tree over
+    /// `num_leafs` leaves. The unused node slots stay unwritten, so on most file systems they
+    /// take no space.
+    pub fn create<P: AsRef<Path>>(path: P, num_leafs: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len((2 * num_leafs * 32) as u64)?;
+        Ok(FileStorage { file, num_nodes: 2 * num_leafs })
+    }
+
+    /// Opens a file written by a tree built in a `FileStorage`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let num_nodes = (file.metadata()?.len() / 32) as usize;
+        Ok(FileStorage { file, num_nodes })
+    }
+}
+
+#[cfg(unix)]
+impl NodeStorage for FileStorage {
+    fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+
+    fn read(&self, position: usize, nodes: &mut [[u8; 32]]) {
+        let mut bytes = vec![0u8; 32 * nodes.len()];
+        self.file.read_exact_at(&mut bytes, (32 * position) as u64).expect("cannot read Merkle nodes");
+        for (node, chunk) in nodes.iter_mut().zip(bytes.chunks_exact(32)) {
+            node.copy_from_slice(chunk);
         }
-        let mut recur_vec = Self::open_(index - leafs.len()/2, &leafs[leafs.len()/2..]);
-        recur_vec.extend_from_slice(&Self::commit_(&leafs[..leafs.len()/2]));
-        return recur_vec;
     }
 
-    fn verify_(root: [u8; 32], index: usize, path: &[&[u8]], leaf: [u8; 32]) -> bool {
-        assert!((0 <= index) && (index < (1 << path.len())), "cannot verify invalid index");
-        if path.len() == 1 {
-            if index == 0 {
-                return root == <[u8;32]>::from(Blake2s256::digest(Self::concat(&leaf, path[0])));
-            }
-            else {
-                return root == <[u8;32]>::from(Blake2s256::digest(Self::concat(path[0], &leaf)));
-            }
+    fn write(&mut self, position: usize, nodes: &[[u8; 32]]) {
+        self.file.write_all_at(&nodes.concat(), (32 * position) as u64).expect("cannot write Merkle nodes");
+    }
+}
+
+// MERKLE TREE
+// ================================================================================================
+
+/// Merkle tree with all internal nodes cached, so that authentication paths are read off in
+/// `O(log n)` instead of being recomputed from the leaves. With the `concurrent` feature the
+/// nodes of every layer are hashed in parallel.
+///
+/// Every internal node hashes the concatenation of `arity` children, where the arity is a power
+/// of two. If the number of leaves is not a power of the arity, the level right below the root
+/// has fewer than `arity` nodes, and the root hashes just those.
+///
+/// Since all layers have a power-of-two number of nodes, they are stored in a flat array of
+/// length `2n`: a layer of `m` nodes occupies `nodes[m..2m]`, the children of node `i` on a level
+/// with fan-out `f` are `f * i, ..., f * i + f - 1`, and `nodes[1]` is the root; `nodes[0]` and
+/// the gaps between layers are unused.
+///
+/// The array is held in memory by default. Any other `NodeStorage`, such as a `FileStorage`, is
+/// filled by `build_in` from a stream of leaf hashes, holding only a few chunks of nodes in
+/// memory at a time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree<S = Vec<[u8; 32]>> {
+    nodes: S,
+    arity: usize,
+}
+
+impl MerkleTree {
+
+    pub fn new(data_array: &[Vec<u8>]) -> Self {
+        Self::with_arity(data_array, 2)
+    }
+
+    pub fn with_arity(data_array: &[Vec<u8>], arity: usize) -> Self {
+        Self::from_leaf_hashes_with_arity(Merkle::hash_leafs(data_array), arity)
+    }
+
+    /// Builds the tree over leaves which are already hashed.
+    pub fn from_leaf_hashes(leafs: Vec<[u8; 32]>) -> Self {
+        Self::from_leaf_hashes_with_arity(leafs, 2)
+    }
+
+    pub fn from_leaf_hashes_with_arity(leafs: Vec<[u8; 32]>, arity: usize) -> Self {
+        let num_leafs = leafs.len();
+        assert!(num_leafs > 0 && num_leafs & (num_leafs - 1) == 0, "length must be power of two");
+        let mut nodes = vec![[0u8; 32]; num_leafs];
+        nodes.extend(leafs);
+
+        // a layer of m nodes at [m, 2m) holds the children of the layer at [m / f, 2m / f)
+        let mut m = num_leafs;
+        for fan_out in Self::fan_outs(num_leafs, arity) {
+            let (parents, children) = nodes.split_at_mut(m);
+            let parents = &mut parents[(m / fan_out)..(2 * m / fan_out)];
+            let hash = |(j, node): (usize, &mut [u8; 32])| {
+                *node = Merkle::hash_children(&children[(fan_out * j)..(fan_out * (j + 1))]);
+            };
+            #[cfg(not(feature = "concurrent"))]
+            parents.iter_mut().enumerate().for_each(hash);
+            #[cfg(feature = "concurrent")]
+            parents.par_iter_mut().enumerate().for_each(hash);
+            m /= fan_out;
+        }
+        MerkleTree { nodes, arity }
+    }
+    /// Returns the fan-out of every level of a tree over `num_leafs` leaves, bottom level first.
+    pub fn fan_outs(num_leafs: usize, arity: usize) -> Vec<usize> {
+        assert!(arity >= 2 && arity.is_power_of_two(), "arity must be a power of two");
+        let mut fan_outs = Vec::new();
+        let mut m = num_leafs;
+        while m > 1 {
+            let fan_out = arity.min(m);
+            fan_outs.push(fan_out);
+            m /= fan_out;
         }
-        else {
-            if index % 2 == 0 {
-                return Self::verify_(root, index >> 1, &path[1..],  Blake2s256::digest(Self::concat(&leaf, path[0])).into());
+        fan_outs
+    }
+
+    /// Number of nodes in an authentication path up to the cap of height `cap_height`.
+    pub fn path_length(num_leafs: usize, arity: usize, cap_height: usize) -> usize {
+        let fan_outs = Self::fan_outs(num_leafs, arity);
+        assert!(cap_height <= fan_outs.len(), "cap height cannot exceed the depth of the tree");
+        fan_outs[..(fan_outs.len() - cap_height)].iter().map(|fan_out| fan_out - 1).sum()
+    }
+
+    /// Number of nodes in the cap of height `cap_height`.
+    pub fn cap_length(num_leafs: usize, arity: usize, cap_height: usize) -> usize {
+        let fan_outs = Self::fan_outs(num_leafs, arity);
+        assert!(cap_height <= fan_outs.len(), "cap height cannot exceed the depth of the tree");
+        fan_outs[(fan_outs.len() - cap_height)..].iter().product()
+    }
+}
+
+impl<S: NodeStorage> MerkleTree<S> {
+
+    /// Builds the tree in `storage` over the leaf hashes yielded by `leafs`, which must number
+    /// half the slots of the storage. Layers are hashed in chunks, so apart from the storage only
+    /// `arity` chunks of nodes are held in memory at a time.
+    pub fn build_in<I>(mut storage: S, leafs: I, arity: usize) -> Self
+    where
+        I: IntoIterator<Item = [u8; 32]>,
+    {
+        let num_leafs = storage.num_nodes() / 2;
+        assert!(num_leafs > 0 && num_leafs & (num_leafs - 1) == 0, "length must be power of two");
+        let fan_outs = MerkleTree::fan_outs(num_leafs, arity);
+
+        let mut leafs = leafs.into_iter();
+        let mut chunk = Vec::with_capacity(BUILD_CHUNK_SIZE);
+        let mut position = num_leafs;
+        while position < 2 * num_leafs {
+            chunk.clear();
+            chunk.extend(leafs.by_ref().take(BUILD_CHUNK_SIZE.min(2 * num_leafs - position)));
+            assert!(!chunk.is_empty(), "fewer leaves than the storage holds");
+            storage.write(position, &chunk);
+            position += chunk.len();
+        }
+        assert!(leafs.next().is_none(), "more leaves than the storage holds");
+
+        // same layout as `MerkleTree::from_leaf_hashes_with_arity`, one chunk of parents at a time
+        let mut m = num_leafs;
+        let mut children = Vec::new();
+        for fan_out in fan_outs {
+            let num_parents = m / fan_out;
+            let mut first = 0;
+            while first < num_parents {
+                let length = BUILD_CHUNK_SIZE.min(num_parents - first);
+                children.resize(fan_out * length, [0u8; 32]);
+                storage.read(m + fan_out * first, &mut children);
+                #[cfg(not(feature = "concurrent"))]
+                let parents = children.chunks(fan_out).map(Merkle::hash_children).collect::<Vec<_>>();
+                #[cfg(feature = "concurrent")]
+                let parents = children.par_chunks(fan_out).map(Merkle::hash_children).collect::<Vec<_>>();
+                storage.write(num_parents + first, &parents);
+                first += length;
             }
-            else {
-                return Self::verify_(root, index >> 1, &path[1..],  Blake2s256::digest(Self::concat(path[0], &leaf)).into());
+            m = num_parents;
+        }
+        MerkleTree { nodes: storage, arity }
+    }
+
+    /// Wraps a storage which already holds a tree of the given arity, e.g. a `FileStorage`
+    /// reopened after `build_in`.
+    pub fn from_storage(storage: S, arity: usize) -> Self {
+        let num_leafs = storage.num_nodes() / 2;
+        assert!(num_leafs > 0 && num_leafs & (num_leafs - 1) == 0, "length must be power of two");
+        MerkleTree { nodes: storage, arity }
+    }
+
+    pub fn into_storage(self) -> S {
+        self.nodes
+    }
+
+    fn node(&self, position: usize) -> [u8; 32] {
+        let mut node = [[0u8; 32]];
+        self.nodes.read(position, &mut node);
+        node[0]
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.node(1)
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn num_leafs(&self) -> usize {
+        self.nodes.num_nodes() / 2
+    }
+
+    /// Number of levels between the leaves and the root.
+    pub fn depth(&self) -> usize {
+        MerkleTree::fan_outs(self.num_leafs(), self.arity).len()
+    }
+
+    pub fn leaf(&self, index: usize) -> [u8; 32] {
+        self.node(self.num_leafs() + index)
+    }
+
+    /// Returns the authentication path of leaf `index`: the `f - 1` siblings on every level with
+    /// fan-out `f` in child order, leaf level first, as checked by `Merkle::verify` and
+    /// `Merkle::verify_with_arity`.
+    pub fn prove(&self, index: usize) -> Vec<[u8; 32]> {
+        assert!(index < self.num_leafs(), "cannot open invalid index");
+        let mut position = self.num_leafs() + index;
+        let mut path = Vec::new();
+        let mut group = Vec::new();
+        for fan_out in MerkleTree::fan_outs(self.num_leafs(), self.arity) {
+            let first = position - position % fan_out;
+            group.resize(fan_out, [0u8; 32]);
+            self.nodes.read(first, &mut group);
+            path.extend(group.iter().enumerate()
+                .filter(|&(t, _)| first + t != position)
+                .map(|(_, &sibling)| sibling));
+            position /= fan_out;
+        }
+        path
+    }
+
+    /// Returns the nodes of the layer `cap_height` levels below the root.
+    pub fn cap(&self, cap_height: usize) -> Vec<[u8; 32]> {
+        let cap_length = MerkleTree::cap_length(self.num_leafs(), self.arity, cap_height);
+        let mut cap = vec![[0u8; 32]; cap_length];
+        self.nodes.read(cap_length, &mut cap);
+        cap
+    }
+
+    /// Returns the authentication path of leaf `index` up to the cap of height `cap_height`, as
+    /// checked by `Merkle::verify_with_cap` and `Merkle::verify_with_arity`.
+    pub fn prove_to_cap(&self, index: usize, cap_height: usize) -> Vec<[u8; 32]> {
+        let mut path = self.prove(index);
+        path.truncate(MerkleTree::path_length(self.num_leafs(), self.arity, cap_height));
+        path
+    }
+
+    /// Returns the sibling nodes needed to authenticate all `indices` at once, as checked by
+    /// `Merkle::verify_many`: bottom layer first and in increasing index order within a layer.
+    /// A node is left out whenever the verifier can compute it from the opened leaves, so paths
+    /// sharing a subtree share nodes.
+    pub fn prove_batch(&self, indices: &[usize]) -> Vec<[u8; 32]> {
+        assert!(indices.iter().all(|&index| index < self.num_leafs()), "cannot open invalid index");
+        let mut known = indices.iter().map(|&index| self.num_leafs() + index).collect::<Vec<usize>>();
+        known.sort_unstable();
+        known.dedup();
+
+        let mut proof = Vec::new();
+        for fan_out in MerkleTree::fan_outs(self.num_leafs(), self.arity) {
+            let mut i = 0;
+            let mut parents = Vec::with_capacity(known.len());
+            while i < known.len() {
+                let parent = known[i] / fan_out;
+                for child in (parent * fan_out)..((parent + 1) * fan_out) {
+                    if i < known.len() && known[i] == child {
+                        i += 1;
+                    } else {
+                        proof.push(self.node(child));
+                    }
+                }
+                parents.push(parent);
             }
+            known = parents;
+        }
+        proof
+    }
+}
+
+// ROW MERKLE TREE
+// ================================================================================================
+
+/// Vector commitment to the rows of a matrix of field elements, e.g. an execution trace with
+/// one column per register. Every leaf is the hash of a full row, so a single authentication
+/// path opens all columns at an index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowMerkleTree {
+    rows: Vec<Vec<FieldElement>>,
+    tree: MerkleTree,
+}
+
+impl RowMerkleTree {
+
+    pub fn from_rows(rows: Vec<Vec<FieldElement>>) -> Self {
+        assert!(!rows.is_empty(), "cannot commit to an empty matrix");
+        let width = rows[0].len();
+        assert!(rows.iter().all(|row| row.len() == width), "all rows must have the same length");
+
+        #[cfg(not(feature = "concurrent"))]
+        let leafs = rows.iter().map(|row| Merkle::hash_row(row)).collect();
+        #[cfg(feature = "concurrent")]
+        let leafs = rows.par_iter().map(|row| Merkle::hash_row(row)).collect();
+        RowMerkleTree {
+            rows,
+            tree: MerkleTree::from_leaf_hashes(leafs),
+        }
+    }
+
+    /// Commits to the matrix whose columns are `columns`, e.g. the codewords of the registers.
+    pub fn from_columns(columns: &[Vec<FieldElement>]) -> Self {
+        assert!(!columns.is_empty(), "cannot commit to an empty matrix");
+        let num_rows = columns[0].len();
+        assert!(columns.iter().all(|column| column.len() == num_rows), "all columns must have the same length");
+        let rows = (0..num_rows)
+            .map(|i| columns.iter().map(|column| column[i]).collect())
+            .collect();
+        Self::from_rows(rows)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    pub fn row(&self, index: usize) -> &[FieldElement] {
+        &self.rows[index]
+    }
+
+    /// Returns the row at `index` together with its authentication path.
+    pub fn open(&self, index: usize) -> Opening {
+        Opening {
+            values: self.rows[index].clone(),
+            path: self.tree.prove(index),
+        }
+    }
+
+    /// Returns the rows at `indices` together with one deduplicated proof for all of them; see
+    /// `MerkleTree::prove_batch`.
+    pub fn open_many(&self, indices: &[usize]) -> (Vec<Vec<FieldElement>>, Vec<[u8; 32]>) {
+        let rows = indices.iter().map(|&index| self.rows[index].clone()).collect();
+        (rows, self.tree.prove_batch(indices))
+    }
+
+    /// Verifies the opening of a row of a matrix of `num_rows` rows.
+    pub fn verify(num_rows: usize, root: [u8; 32], index: usize, opening: &Opening) -> bool {
+        Merkle::verify_(num_rows, 2, &[root], index, &opening.path, Merkle::hash_row(&opening.values))
+    }
+
+    /// Verifies a batch proof of a matrix of `num_rows` rows.
+    pub fn verify_many(num_rows: usize, root: [u8; 32], indices: &[usize], rows: &[Vec<FieldElement>],
+        proof: &[[u8; 32]]) -> bool
+    {
+        let leafs = rows.iter().map(|row| Merkle::hash_row(row)).collect::<Vec<[u8; 32]>>();
+        Merkle::verify_many_with_arity_(2, num_rows, root, indices, &leafs, proof)
+    }
+}
+
+// SALTED MERKLE TREE
+// ================================================================================================
+
+/// Hiding vector commitment for zero-knowledge proofs. Every leaf hashes a random salt of
+/// `SALT_BYTES` bytes followed by the data, so the root and the paths of opened leaves reveal
+/// nothing about the leaves left unopened, even when these can take only a few values. The salt of
+/// a leaf is revealed only together with the leaf itself.
+///
+/// The salts are drawn from the given RNG; pass a seeded one for reproducible commitments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaltedMerkleTree {
+    salts: Vec<[u8; SALT_BYTES]>,
+    tree: MerkleTree,
+}
+
+impl SaltedMerkleTree {
+
+    pub fn new<R: RngCore>(data_array: &[Vec<u8>], rng: &mut R) -> Self {
+        Self::with_arity(data_array, 2, rng)
+    }
+
+    pub fn with_arity<R: RngCore>(data_array: &[Vec<u8>], arity: usize, rng: &mut R) -> Self {
+        let salts = data_array.iter()
+            .map(|_| {
+                let mut salt = [0u8; SALT_BYTES];
+                rng.fill_bytes(&mut salt);
+                salt
+            })
+            .collect();
+        Self::from_salts(data_array, salts, arity)
+    }
+
+    /// Builds the tree with the given salts, one per data element.
+    pub fn from_salts(data_array: &[Vec<u8>], salts: Vec<[u8; SALT_BYTES]>, arity: usize) -> Self {
+        assert!(salts.len() == data_array.len(), "number of salts does not match number of leaves");
+        #[cfg(not(feature = "concurrent"))]
+        let leafs = salts.iter().zip(data_array.iter())
+            .map(|(salt, data)| Merkle::hash_salted(salt, data))
+            .collect();
+        #[cfg(feature = "concurrent")]
+        let leafs = salts.par_iter().zip(data_array.par_iter())
+            .map(|(salt, data)| Merkle::hash_salted(salt, data))
+            .collect();
+        SaltedMerkleTree {
+            salts,
+            tree: MerkleTree::from_leaf_hashes_with_arity(leafs, arity),
+        }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    pub fn cap(&self, cap_height: usize) -> Vec<[u8; 32]> {
+        self.tree.cap(cap_height)
+    }
+
+    pub fn num_leafs(&self) -> usize {
+        self.tree.num_leafs()
+    }
+
+    pub fn arity(&self) -> usize {
+        self.tree.arity()
+    }
+
+    pub fn salt(&self, index: usize) -> [u8; SALT_BYTES] {
+        self.salts[index]
+    }
+
+    /// Returns the salt of leaf `index` together with its authentication path.
+    pub fn open(&self, index: usize) -> ([u8; SALT_BYTES], Vec<[u8; 32]>) {
+        (self.salts[index], self.tree.prove(index))
+    }
+
+    /// Returns the salt of leaf `index` together with its authentication path up to the cap.
+    pub fn open_to_cap(&self, index: usize, cap_height: usize) -> ([u8; SALT_BYTES], Vec<[u8; 32]>) {
+        (self.salts[index], self.tree.prove_to_cap(index, cap_height))
+    }
+
+    /// Returns the salts of the leaves at `indices` together with one deduplicated proof for all
+    /// of them; see `MerkleTree::prove_batch`.
+    pub fn open_many(&self, indices: &[usize]) -> (Vec<[u8; SALT_BYTES]>, Vec<[u8; 32]>) {
+        let salts = indices.iter().map(|&index| self.salts[index]).collect();
+        (salts, self.tree.prove_batch(indices))
+    }
+
+    /// Verifies a path in a binary salted tree over `num_leafs` leaves.
+    pub fn verify(num_leafs: usize, root: [u8; 32], index: usize, salt: &[u8; SALT_BYTES],
+        path: &[[u8; 32]], data_element: &[u8]) -> bool
+    {
+        Merkle::verify_(num_leafs, 2, &[root], index, path, Merkle::hash_salted(salt, data_element))
+    }
+
+    /// Verifies a path in a salted tree of the given arity against a cap, or against `&[root]`.
+    pub fn verify_with_arity(arity: usize, num_leafs: usize, cap: &[[u8; 32]], index: usize,
+        salt: &[u8; SALT_BYTES], path: &[[u8; 32]], data_element: &[u8]) -> bool
+    {
+        assert!(arity >= 2 && arity.is_power_of_two(), "arity must be a power of two");
+        Merkle::verify_(num_leafs, arity, cap, index, path, Merkle::hash_salted(salt, data_element))
+    }
+
+    /// Verifies a batch proof of a binary salted tree over `num_leafs` leaves.
+    pub fn verify_many(num_leafs: usize, root: [u8; 32], indices: &[usize], salts: &[[u8; SALT_BYTES]],
+        data_elements: &[Vec<u8>], proof: &[[u8; 32]]) -> bool
+    {
+        Self::verify_many_with_arity(2, num_leafs, root, indices, salts, data_elements, proof)
+    }
+
+    /// Verifies a batch proof of a salted tree of the given arity over `num_leafs` leaves.
+    pub fn verify_many_with_arity(arity: usize, num_leafs: usize, root: [u8; 32], indices: &[usize],
+        salts: &[[u8; SALT_BYTES]], data_elements: &[Vec<u8>], proof: &[[u8; 32]]) -> bool
+    {
+        if salts.len() != data_elements.len() {
+            return false;
         }
+        let leafs = Self::hash_leafs(salts, data_elements);
+        Merkle::verify_many_with_arity_(arity, num_leafs, root, indices, &leafs, proof)
     }
 
-    // pub fn commit(data_array: &[[u8;32]]) -> [u8; 32] {
-    //     return Self::commit_(data_array.iter().map(|x| Blake2s256::digest(x).into()).collect::<Vec<u8>>().try_into().unwrap())
-    // }
-}
\ No newline at end of file
+    fn hash_leafs(salts: &[[u8; SALT_BYTES]], data_elements: &[Vec<u8>]) -> Vec<[u8; 32]> {
+        salts.iter().zip(data_elements.iter())
+            .map(|(salt, data)| Merkle::hash_salted(salt, data))
+            .collect()
+    }
+}
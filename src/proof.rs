@@ -0,0 +1,365 @@
+use crate::math::{FieldElement, ELEMENT_BYTES, errors::DeserializationError};
+use crate::proof_stream::ProofStream;
+use crate::fri::Fri;
+use crate::deep::OodFrame;
+use crate::stark::Stark;
+
+const DIGEST_BYTES: usize = 32;
+const NONCE_BYTES: usize = 8;
+
+// OPENING
+// ================================================================================================
+
+/// Values stored in one Merkle leaf together with the authentication path of that leaf.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening {
+    pub values: Vec<FieldElement>,
+    pub path: Vec<[u8; 32]>,
+}
+
+impl Opening {
+
+    pub fn values_size(&self) -> usize {
+        self.values.len() * ELEMENT_BYTES
+    }
+
+    pub fn path_size(&self) -> usize {
+        self.path.len() * DIGEST_BYTES
+    }
+
+    // reads `number` openings laid out as in the proof stream: all leaves, then all paths
+    fn read_many(proof_stream: &mut ProofStream, number: usize, width: usize, depth: usize)
+        -> Result<Vec<Self>, DeserializationError>
+    {
+        let mut values = Vec::with_capacity(number);
+        for _ in 0..number {
+            let leaf = FieldElement::read_elements(&proof_stream.try_pull()?)?;
+            if leaf.len() != width {
+                return Err(DeserializationError::InvalidValue(format!(
+                    "expected {} elements in leaf, but got {}", width, leaf.len())));
+            }
+            values.push(leaf);
+        }
+        let mut openings = Vec::with_capacity(number);
+        for values in values {
+            let path = read_digests(&proof_stream.try_pull()?)?;
+            if path.len() != depth {
+                return Err(DeserializationError::InvalidValue(format!(
+                    "expected authentication path of length {}, but got {}", depth, path.len())));
+            }
+            openings.push(Opening { values, path });
+        }
+        Ok(openings)
+    }
+
+    pub(crate) fn write_many(openings: &[Self], proof_stream: &mut ProofStream) {
+        for opening in openings {
+            proof_stream.push(FieldElement::elements_as_bytes(&opening.values).to_vec());
+        }
+        for opening in openings {
+            proof_stream.push(opening.path.concat());
+        }
+    }
+}
+
+// FRI PROOF
+// ================================================================================================
+
+/// Typed view of the objects `Fri::prove` pushes into a `ProofStream`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FriProof {
+    /// Merkle caps of all layers but the last one; a single root each when the cap height is 0.
+    pub layer_caps: Vec<Vec<[u8; 32]>>,
+    /// The last layer, as a codeword or as coefficients depending on the `Fri` parameters.
+    pub remainder: Vec<FieldElement>,
+    /// Proof-of-work nonce; present iff grinding is enabled and there are queries.
+    pub nonce: Option<u64>,
+    /// `query_openings[r][s]` opens the coset of the `s`-th query in the `r`-th layer.
+    pub query_openings: Vec<Vec<Opening>>,
+}
+
+/// Number of bytes taken by each component of a `FriProof`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FriProofSize {
+    pub layer_caps: usize,
+    pub remainder: usize,
+    pub nonce: usize,
+    pub query_values: usize,
+    pub query_paths: usize,
+}
+
+impl FriProofSize {
+
+    pub fn total(&self) -> usize {
+        self.layer_caps + self.remainder + self.nonce + self.query_values + self.query_paths
+    }
+}
+
+impl FriProof {
+
+    /// Reads a proof produced by `fri.prove` from the current position of the stream.
+    pub fn read(fri: &Fri, proof_stream: &mut ProofStream) -> Result<Self, DeserializationError> {
+        let num_rounds = fri.num_rounds() as usize;
+        let folding_factor = fri.folding_factor as usize;
+        let num_queries = fri.num_colinearity_tests as usize;
+
+        let mut layer_caps = Vec::with_capacity(num_rounds.saturating_sub(1));
+        let mut num_leafs = fri.domain_length as usize;
+        for _ in 1..num_rounds {
+            num_leafs /= folding_factor;
+            let cap = read_digests(&proof_stream.try_pull()?)?;
+            let cap_length = fri.layer_cap_length(num_leafs);
+            if cap.len() != cap_length {
+                return Err(DeserializationError::InvalidValue(format!(
+                    "expected a cap of {} nodes, but got {}", cap_length, cap.len())));
+            }
+            layer_caps.push(cap);
+        }
+
+        let remainder = FieldElement::read_elements(&proof_stream.try_pull()?)?;
+        let last_layer_length = fri.last_layer_length() as usize;
+        let max_remainder_length = if fri.last_layer_as_coefficients {
+            last_layer_length / fri.expansion_factor as usize
+        } else {
+            last_layer_length
+        };
+        if remainder.len() > max_remainder_length
+            || (!fri.last_layer_as_coefficients && remainder.len() != last_layer_length)
+        {
+            return Err(DeserializationError::InvalidValue(format!(
+                "unexpected remainder length {}", remainder.len())));
+        }
+
+        let mut nonce = None;
+        let mut query_openings = Vec::with_capacity(layer_caps.len());
+        if num_rounds >= 2 {
+            if fri.grinding_bits > 0 {
+                let bytes: [u8; NONCE_BYTES] = proof_stream.try_pull()?.try_into()
+                    .map_err(|_| DeserializationError::InvalidValue("nonce must be 8 bytes".to_string()))?;
+                nonce = Some(u64::from_le_bytes(bytes));
+            }
+
+            let mut codeword_length = fri.domain_length as usize;
+            for _ in 1..num_rounds {
+                let num_leafs = codeword_length / folding_factor;
+                let path_length = fri.layer_path_length(num_leafs);
+                query_openings.push(Opening::read_many(proof_stream, num_queries, folding_factor, path_length)?);
+                codeword_length = num_leafs;
+            }
+        }
+
+        Ok(FriProof {
+            layer_caps,
+            remainder,
+            nonce,
+            query_openings,
+        })
+    }
+
+    /// Pushes the proof into the stream in the order `Fri::verify` expects.
+    pub fn write(&self, proof_stream: &mut ProofStream) {
+        for cap in self.layer_caps.iter() {
+            proof_stream.push(cap.concat());
+        }
+        proof_stream.push(FieldElement::elements_as_bytes(&self.remainder).to_vec());
+        if let Some(nonce) = self.nonce {
+            proof_stream.push(nonce.to_le_bytes().to_vec());
+        }
+        for openings in self.query_openings.iter() {
+            Opening::write_many(openings, proof_stream);
+        }
+    }
+
+    pub fn to_proof_stream(&self) -> ProofStream {
+        let mut proof_stream = ProofStream::new();
+        self.write(&mut proof_stream);
+        proof_stream
+    }
+
+    pub fn size(&self) -> FriProofSize {
+        let openings = self.query_openings.iter().flatten();
+        FriProofSize {
+            layer_caps: self.layer_caps.iter().map(|cap| cap.len() * DIGEST_BYTES).sum(),
+            remainder: self.remainder.len() * ELEMENT_BYTES,
+            nonce: if self.nonce.is_some() { NONCE_BYTES } else { 0 },
+            query_values: openings.clone().map(Opening::values_size).sum(),
+            query_paths: openings.map(Opening::path_size).sum(),
+        }
+    }
+}
+
+// STARK PROOF
+// ================================================================================================
+
+/// Typed STARK proof. The trace is committed row by row over the FRI domain, in zk mode the
+/// randomizer codeword in a tree of its own, and likewise each quotient codeword. The objects are
+/// laid out in the proof stream as follows:
+///
+/// 1. the trace root, the randomizer root in zk mode, then one root per quotient codeword;
+/// 2. the out-of-domain frame, followed in zk mode by the value of the randomizer at `z`;
+/// 3. the FRI proof;
+/// 4. for every position of every queried coset of the first FRI layer: the trace rows at `x`,
+///    the trace rows at `omicron * x`, the randomizer values at `x` in zk mode, then the values of
+///    every quotient at `x`. Each group is written like the FRI openings, all leaves first and
+///    then all paths.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StarkProof {
+    pub trace_root: [u8; 32],
+    /// Root of the randomizer codeword; present iff the proof is zero-knowledge.
+    pub randomizer_root: Option<[u8; 32]>,
+    pub quotient_roots: Vec<[u8; 32]>,
+    pub ood_frame: OodFrame,
+    /// Value of the randomizer polynomial at `z`; present iff the proof is zero-knowledge.
+    pub ood_randomizer_value: Option<FieldElement>,
+    pub fri_proof: FriProof,
+    pub trace_rows: Vec<Opening>,
+    pub next_trace_rows: Vec<Opening>,
+    /// Openings of the randomizer codeword; empty unless the proof is zero-knowledge.
+    pub randomizer_rows: Vec<Opening>,
+    /// `quotient_rows[i]` holds the openings of the `i`-th quotient codeword.
+    pub quotient_rows: Vec<Vec<Opening>>,
+}
+
+/// Number of bytes taken by each component of a `StarkProof`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StarkProofSize {
+    pub roots: usize,
+    pub ood_frame: usize,
+    pub trace_values: usize,
+    /// Opened values of the randomizer and of the quotients.
+    pub quotient_values: usize,
+    pub paths: usize,
+    pub fri: FriProofSize,
+}
+
+impl StarkProofSize {
+
+    pub fn total(&self) -> usize {
+        self.roots + self.ood_frame + self.trace_values + self.quotient_values + self.paths
+            + self.fri.total()
+    }
+}
+
+impl StarkProof {
+
+    /// Number of positions of the FRI domain at which the trace and quotients are opened.
+    pub fn num_opened_positions(stark: &Stark) -> usize {
+        let fri = stark.fri();
+        (fri.num_colinearity_tests * fri.folding_factor) as usize
+    }
+
+    pub fn read(stark: &Stark, num_quotients: usize, proof_stream: &mut ProofStream)
+        -> Result<Self, DeserializationError>
+    {
+        let trace_root = read_digest(&proof_stream.try_pull()?)?;
+        let randomizer_root = match stark.zk {
+            true => Some(read_digest(&proof_stream.try_pull()?)?),
+            false => None,
+        };
+        let mut quotient_roots = Vec::with_capacity(num_quotients);
+        for _ in 0..num_quotients {
+            quotient_roots.push(read_digest(&proof_stream.try_pull()?)?);
+        }
+
+        let ood_frame = OodFrame::from_bytes(&proof_stream.try_pull()?, stark.num_registers)?;
+        let ood_randomizer_value = match stark.zk {
+            true => Some(read_element(&proof_stream.try_pull()?)?),
+            false => None,
+        };
+
+        let fri_proof = FriProof::read(&stark.fri(), proof_stream)?;
+
+        let number = Self::num_opened_positions(stark);
+        let depth = stark.fri_domain_length.trailing_zeros() as usize;
+        let trace_rows = Opening::read_many(proof_stream, number, stark.num_registers, depth)?;
+        let next_trace_rows = Opening::read_many(proof_stream, number, stark.num_registers, depth)?;
+        let randomizer_rows = match stark.zk {
+            true => Opening::read_many(proof_stream, number, 1, depth)?,
+            false => Vec::new(),
+        };
+        let mut quotient_rows = Vec::with_capacity(num_quotients);
+        for _ in 0..num_quotients {
+            quotient_rows.push(Opening::read_many(proof_stream, number, 1, depth)?);
+        }
+
+        Ok(StarkProof {
+            trace_root,
+            randomizer_root,
+            quotient_roots,
+            ood_frame,
+            ood_randomizer_value,
+            fri_proof,
+            trace_rows,
+            next_trace_rows,
+            randomizer_rows,
+            quotient_rows,
+        })
+    }
+
+    pub fn write(&self, proof_stream: &mut ProofStream) {
+        proof_stream.push(self.trace_root.to_vec());
+        if let Some(root) = self.randomizer_root {
+            proof_stream.push(root.to_vec());
+        }
+        for root in self.quotient_roots.iter() {
+            proof_stream.push(root.to_vec());
+        }
+        proof_stream.push(self.ood_frame.to_bytes());
+        if let Some(value) = self.ood_randomizer_value {
+            proof_stream.push(FieldElement::elements_as_bytes(&[value]).to_vec());
+        }
+        self.fri_proof.write(proof_stream);
+        Opening::write_many(&self.trace_rows, proof_stream);
+        Opening::write_many(&self.next_trace_rows, proof_stream);
+        Opening::write_many(&self.randomizer_rows, proof_stream);
+        for openings in self.quotient_rows.iter() {
+            Opening::write_many(openings, proof_stream);
+        }
+    }
+
+    pub fn to_proof_stream(&self) -> ProofStream {
+        let mut proof_stream = ProofStream::new();
+        self.write(&mut proof_stream);
+        proof_stream
+    }
+
+    pub fn size(&self) -> StarkProofSize {
+        let trace_openings = self.trace_rows.iter().chain(self.next_trace_rows.iter());
+        let quotient_openings = self.randomizer_rows.iter().chain(self.quotient_rows.iter().flatten());
+        let num_roots = 1 + self.randomizer_root.iter().count() + self.quotient_roots.len();
+        let num_ood_values = 2 * self.ood_frame.current.len() + 1 + self.ood_randomizer_value.iter().count();
+        StarkProofSize {
+            roots: num_roots * DIGEST_BYTES,
+            ood_frame: num_ood_values * ELEMENT_BYTES,
+            trace_values: trace_openings.clone().map(Opening::values_size).sum(),
+            quotient_values: quotient_openings.clone().map(Opening::values_size).sum(),
+            paths: trace_openings.chain(quotient_openings).map(Opening::path_size).sum(),
+            fri: self.fri_proof.size(),
+        }
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+fn read_digest(bytes: &[u8]) -> Result<[u8; 32], DeserializationError> {
+    bytes.try_into()
+        .map_err(|_| DeserializationError::InvalidValue(format!(
+            "expected a digest of {} bytes, but got {}", DIGEST_BYTES, bytes.len())))
+}
+
+fn read_element(bytes: &[u8]) -> Result<FieldElement, DeserializationError> {
+    match FieldElement::read_elements(bytes)?[..] {
+        [element] => Ok(element),
+        ref elements => Err(DeserializationError::InvalidValue(format!(
+            "expected a single field element, but got {}", elements.len()))),
+    }
+}
+
+fn read_digests(bytes: &[u8]) -> Result<Vec<[u8; 32]>, DeserializationError> {
+    if !bytes.len().is_multiple_of(DIGEST_BYTES) {
+        return Err(DeserializationError::InvalidValue(format!(
+            "authentication path of {} bytes is not a multiple of {}", bytes.len(), DIGEST_BYTES)));
+    }
+    Ok(bytes.chunks(DIGEST_BYTES).map(|chunk| chunk.try_into().unwrap()).collect())
+}
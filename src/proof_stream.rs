@@ -1,7 +1,7 @@
 use sha3::{Sha3_256, Digest};
-use std::slice;
 use crate::math::errors::DeserializationError;
 
+#[derive(Clone, Debug, Default)]
 pub struct ProofStream{
     pub objects: Vec<Vec<u8>>,
     pub read_index: usize
@@ -16,27 +16,41 @@ impl ProofStream {
         }
     }
 
-    pub fn push(mut self, obj: Vec<u8>) {
+    pub fn push(&mut self, obj: Vec<u8>) {
         self.objects.push(obj);
     }
 
-    pub fn pull(mut self) -> Vec<u8> {
+    pub fn pull(&mut self) -> Vec<u8> {
         assert!(self.read_index < self.objects.len(), "ProofStream: queue empty");
         let obj = &self.objects[self.read_index];
         self.read_index += 1;
-        return obj.to_vec();
+        obj.to_vec()
     }
 
-    fn vf_to_u8(v: &[Vec<u8>]) -> &[u8] {
-        unsafe { std::slice::from_raw_parts(v.as_ptr() as *const u8, v.len() * 4) }
+    /// Like `pull`, but reports an exhausted stream as an error instead of panicking.
+    pub fn try_pull(&mut self) -> Result<Vec<u8>, DeserializationError> {
+        if self.read_index >= self.objects.len() {
+            return Err(DeserializationError::UnexpectedEOF);
+        }
+        Ok(self.pull())
+    }
+
+    // every object is prefixed with its length so that the hash input is unambiguous
+    fn hash_objects(objects: &[Vec<u8>]) -> [u8; 32] {
+        let mut hasher = Sha3_256::new();
+        for obj in objects {
+            hasher.update((obj.len() as u64).to_le_bytes());
+            hasher.update(obj);
+        }
+        hasher.finalize().into()
     }
 
     pub fn prover_fiat_shamir(&self) -> [u8; 32] {
-        Sha3_256::digest(&mut Self::vf_to_u8(&self.objects)).into()
+        Self::hash_objects(&self.objects)
     }
 
     pub fn verifier_fiat_shamir(&self) -> [u8; 32] {
-        Sha3_256::digest(&mut Self::vf_to_u8(&self.objects[0..self.read_index])).into()
+        Self::hash_objects(&self.objects[0..self.read_index])
     }
 }
 
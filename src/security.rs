@@ -0,0 +1,257 @@
+use crate::math::M;
+use crate::fri::{Fri, FriOptions};
+
+/// Output size of Blake2s, which is used for the Merkle commitments and the Fiat-Shamir
+/// transform.
+pub const HASH_OUTPUT_BITS: u32 = 256;
+
+// Multiplicity parameters of the Guruswami-Sudan decoder tried in the proven bound.
+const MIN_MULTIPLICITY: u32 = 3;
+const MAX_MULTIPLICITY: u32 = 256;
+
+// SECURITY LEVEL
+// ================================================================================================
+
+/// Estimated security of a set of FRI parameters, in bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecurityLevel {
+    /// Security under the ethSTARK conjecture that codewords far from the code are caught by
+    /// every query with probability `1 - rho`.
+    pub conjectured: u32,
+    /// Security proven up to the Johnson bound.
+    pub proven: u32,
+}
+
+impl SecurityLevel {
+
+    pub fn new(domain_length: u128, expansion_factor: u128, num_colinearity_tests: u128,
+        folding_factor: u128, grinding_bits: u128, hash_output_bits: u32) -> Self
+    {
+        SecurityLevel {
+            conjectured: conjectured_security(domain_length, expansion_factor, num_colinearity_tests,
+                folding_factor, grinding_bits, hash_output_bits),
+            proven: proven_security(domain_length, expansion_factor, num_colinearity_tests,
+                folding_factor, grinding_bits, hash_output_bits),
+        }
+    }
+
+    pub fn of(fri: &Fri) -> Self {
+        Self::new(fri.domain_length, fri.expansion_factor, fri.num_colinearity_tests,
+            fri.folding_factor, fri.grinding_bits, HASH_OUTPUT_BITS)
+    }
+
+    /// Security of a STARK whose low degree test is `fri` and whose out-of-domain check compares
+    /// polynomials of degree below `degree_bound`; see `conjectured_stark_security` and
+    /// `proven_stark_security`.
+    pub fn of_stark(fri: &Fri, degree_bound: u128) -> Self {
+        SecurityLevel {
+            conjectured: conjectured_stark_security(fri.domain_length, fri.expansion_factor,
+                fri.num_colinearity_tests, fri.folding_factor, fri.grinding_bits, degree_bound,
+                HASH_OUTPUT_BITS),
+            proven: proven_stark_security(fri.domain_length, fri.expansion_factor,
+                fri.num_colinearity_tests, fri.folding_factor, fri.grinding_bits, degree_bound,
+                HASH_OUTPUT_BITS),
+        }
+    }
+}
+
+/// Number of bits of the field modulus `M`.
+pub fn field_bits() -> f64 {
+    (M as f64).log2()
+}
+
+/// Security against finding collisions in a hash with the given output size.
+pub fn collision_resistance(hash_output_bits: u32) -> u32 {
+    hash_output_bits / 2
+}
+
+/// Conjectured security: every query contributes `log2(expansion_factor)` bits and grinding
+/// adds its bits on top; the result is capped by the probability of a bad challenge in a field
+/// of `field_bits()` bits over a domain of `domain_length` points, and by the collision
+/// resistance of the hash. Folding `folding_factor` values with the powers of one challenge
+/// multiplies the probability of a bad challenge by `folding_factor - 1`.
+pub fn conjectured_security(domain_length: u128, expansion_factor: u128, num_colinearity_tests: u128,
+    folding_factor: u128, grinding_bits: u128, hash_output_bits: u32) -> u32
+{
+    let field_security = field_bits() - ((folding_factor - 1) as f64 * domain_length as f64).log2();
+    let query_security = (num_colinearity_tests as f64) * (expansion_factor as f64).log2()
+        + grinding_bits as f64;
+    let security = field_security.min(query_security) - 1.0;
+    (security.max(0.0) as u32).min(collision_resistance(hash_output_bits))
+}
+
+/// Proven security up to the Johnson bound, maximized over the multiplicity `m` of the
+/// list-decoding analysis (BCIKS20, Theorem 8.3, as used in ethSTARK):
+///
+/// - the commit phase fails with probability `(f - 1) (m + 1/2)^7 / (2 rho^(3/2)) * n^2 / |F|`
+///   for a folding factor `f`, as the folded values form a curve of degree `f - 1` in the
+///   challenge (BCIKS20, Theorem 8.3 for curves);
+/// - every query is passed by a far codeword with probability at most
+///   `alpha = (1 + 1/(2m)) * sqrt(rho)`, and grinding adds its bits on top.
+pub fn proven_security(domain_length: u128, expansion_factor: u128, num_colinearity_tests: u128,
+    folding_factor: u128, grinding_bits: u128, hash_output_bits: u32) -> u32
+{
+    let security = (MIN_MULTIPLICITY..=MAX_MULTIPLICITY)
+        .map(|m| proven_security_for_multiplicity(domain_length, expansion_factor,
+            num_colinearity_tests, folding_factor, grinding_bits, m as f64))
+        .fold(0.0, f64::max);
+    (security as u32).min(collision_resistance(hash_output_bits))
+}
+
+fn proven_security_for_multiplicity(domain_length: u128, expansion_factor: u128,
+    num_colinearity_tests: u128, folding_factor: u128, grinding_bits: u128, m: f64) -> f64
+{
+    let rho = 1.0 / expansion_factor as f64;
+    let alpha = (1.0 + 0.5 / m) * rho.sqrt();
+    let n = domain_length as f64;
+
+    let commit_security = field_bits()
+        - ((folding_factor - 1) as f64 * (m + 0.5).powi(7) / (2.0 * rho.powf(1.5)) * n * n).log2();
+    let query_security = grinding_bits as f64 - (num_colinearity_tests as f64) * alpha.log2();
+
+    // union bound over both failure events
+    (commit_security.min(query_security) - 1.0).max(0.0)
+}
+
+// STARK SECURITY
+// ================================================================================================
+
+/// Conjectured security of a STARK: the minimum of the FRI estimate and the DEEP-ALI terms for a
+/// list of a single codeword, see `deep_ali_security`.
+pub fn conjectured_stark_security(domain_length: u128, expansion_factor: u128,
+    num_colinearity_tests: u128, folding_factor: u128, grinding_bits: u128, degree_bound: u128,
+    hash_output_bits: u32) -> u32
+{
+    let fri = conjectured_security(domain_length, expansion_factor, num_colinearity_tests,
+        folding_factor, grinding_bits, hash_output_bits);
+    let deep_ali = (deep_ali_security(degree_bound, 1.0) - 1.0).max(0.0);
+    fri.min(deep_ali as u32)
+}
+
+/// Proven security of a STARK up to the Johnson bound: for every multiplicity `m` the FRI terms
+/// are combined with the DEEP-ALI terms for the list size `(m + 1/2) / sqrt(rho)` of the same
+/// decoding radius (ethSTARK, Theorem 2), and the best `m` is kept.
+pub fn proven_stark_security(domain_length: u128, expansion_factor: u128,
+    num_colinearity_tests: u128, folding_factor: u128, grinding_bits: u128, degree_bound: u128,
+    hash_output_bits: u32) -> u32
+{
+    let rho = 1.0 / expansion_factor as f64;
+    let security = (MIN_MULTIPLICITY..=MAX_MULTIPLICITY)
+        .map(|m| {
+            let fri = proven_security_for_multiplicity(domain_length, expansion_factor,
+                num_colinearity_tests, folding_factor, grinding_bits, m as f64);
+            let list_size = (m as f64 + 0.5) / rho.sqrt();
+            fri.min(deep_ali_security(degree_bound, list_size) - 1.0).max(0.0)
+        })
+        .fold(0.0, f64::max);
+    (security as u32).min(collision_resistance(hash_output_bits))
+}
+
+/// Soundness in bits of the reduction from the constraints to a single low degree test, when
+/// FRI may accept any of `list_size` codewords: the weights of the nonlinear combination (ALI)
+/// cancel an over-degree quotient with probability `list_size / |F|`, and the out-of-domain
+/// point (DEEP) is a root of the difference of two distinct polynomials of degree below
+/// `degree_bound` with probability `list_size * degree_bound / |F|`.
+pub fn deep_ali_security(degree_bound: u128, list_size: f64) -> f64 {
+    let ali = field_bits() - list_size.log2();
+    let deep = field_bits() - (list_size * degree_bound as f64).log2();
+    ali.min(deep)
+}
+
+// PARAMETER SEARCH
+// ================================================================================================
+
+/// FRI parameters found by `optimize`, together with the resulting proof size and security.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParameterChoice {
+    pub expansion_factor: u128,
+    pub num_colinearity_tests: u128,
+    pub folding_factor: u128,
+    pub grinding_bits: u128,
+    pub last_layer_as_coefficients: bool,
+    pub proof_size: usize,
+    pub security: SecurityLevel,
+}
+
+impl ParameterChoice {
+
+    pub fn fri(&self, degree_bound: u128) -> Fri {
+        FriOptions::new(degree_bound * self.expansion_factor, self.expansion_factor,
+            self.num_colinearity_tests)
+            .with_folding_factor(self.folding_factor)
+            .with_grinding_bits(self.grinding_bits)
+            .with_last_layer_as_coefficients(self.last_layer_as_coefficients)
+            .build()
+            .expect("parameters were validated during the search")
+    }
+}
+
+/// Searches expansion factors up to `max_expansion_factor`, all folding factors, grinding up to
+/// `max_grinding_bits` and both last layer encodings for the parameters with the smallest FRI
+/// proof for polynomials of degree less than `degree_bound` (a power of two) that reach
+/// `target_bits` of security, proven security if `proven` is set and conjectured otherwise.
+pub fn optimize(degree_bound: u128, target_bits: u32, max_expansion_factor: u128,
+    max_grinding_bits: u128, proven: bool) -> Option<ParameterChoice>
+{
+    let mut best: Option<ParameterChoice> = None;
+    let mut expansion_factor = 2;
+    while expansion_factor <= max_expansion_factor {
+        let domain_length = degree_bound * expansion_factor;
+        for folding_factor in [2, 4, 8, 16] {
+            for grinding_bits in 0..=max_grinding_bits {
+                for last_layer_as_coefficients in [false, true] {
+                    let choice = min_colinearity_tests(domain_length, expansion_factor, folding_factor,
+                        grinding_bits, last_layer_as_coefficients, target_bits, proven);
+                    if let Some(choice) = choice {
+                        if best.is_none_or(|best| choice.proof_size < best.proof_size) {
+                            best = Some(choice);
+                        }
+                    }
+                }
+            }
+        }
+        expansion_factor *= 2;
+    }
+    best
+}
+
+// finds the smallest number of colinearity tests meeting the target, if any; security grows
+// with the number of tests, so the first valid one is the smallest
+fn min_colinearity_tests(domain_length: u128, expansion_factor: u128, folding_factor: u128,
+    grinding_bits: u128, last_layer_as_coefficients: bool, target_bits: u32, proven: bool)
+    -> Option<ParameterChoice>
+{
+    let bits = |security: SecurityLevel| if proven { security.proven } else { security.conjectured };
+
+    // the field and hash bounds cap the security no matter how many tests are run
+    let limit = SecurityLevel::new(domain_length, expansion_factor, domain_length, folding_factor,
+        grinding_bits, HASH_OUTPUT_BITS);
+    if bits(limit) < target_bits {
+        return None;
+    }
+
+    let mut num_colinearity_tests = 1;
+    while num_colinearity_tests <= domain_length {
+        let security = SecurityLevel::new(domain_length, expansion_factor, num_colinearity_tests,
+            folding_factor, grinding_bits, HASH_OUTPUT_BITS);
+        if bits(security) >= target_bits {
+            let fri = FriOptions::new(domain_length, expansion_factor, num_colinearity_tests)
+                .with_folding_factor(folding_factor)
+                .with_grinding_bits(grinding_bits)
+                .with_last_layer_as_coefficients(last_layer_as_coefficients)
+                .build()
+                .ok()?;
+            return Some(ParameterChoice {
+                expansion_factor,
+                num_colinearity_tests,
+                folding_factor,
+                grinding_bits,
+                last_layer_as_coefficients,
+                proof_size: fri.proof_size(),
+                security,
+            });
+        }
+        num_colinearity_tests += 1;
+    }
+    None
+}
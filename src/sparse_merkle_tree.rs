@@ -0,0 +1,214 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+use crate::merkle_tree::Merkle;
+
+/// Number of levels of a `SparseMerkleTree`, one per bit of a key.
+pub const SPARSE_DEPTH: usize = 256;
+
+// hash of an empty leaf
+const EMPTY_LEAF: [u8; 32] = [0u8; 32];
+
+// SPARSE MERKLE TREE
+// ================================================================================================
+
+/// Merkle tree with `2^256` leaves, one per 256-bit key, of which all but the inserted ones are
+/// empty. The bits of a key, most significant bit of the first byte first, spell out the path
+/// from the root to its leaf.
+///
+/// An occupied leaf is `Merkle::hash_leaf(value)`, an empty leaf is all zeros, and an internal
+/// node hashes its two children like the binary `MerkleTree` does. A subtree without any
+/// occupied leaf therefore has a default hash depending only on its height, and only nodes
+/// differing from their default are stored.
+///
+/// Authentication paths run from the leaf to the root and leave out default siblings; see
+/// `SparseMerkleProof`. The same proof shows that a key holds a value or, if the leaf is empty,
+/// that the key is absent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SparseMerkleTree {
+    leaves: HashMap<[u8; 32], Vec<u8>>,
+    // non-default nodes by height above the leaves and key with the bits below that height cleared
+    nodes: HashMap<(usize, [u8; 32]), [u8; 32]>,
+}
+
+/// Authentication path of a key in a `SparseMerkleTree`, bottom level first. Bit `h` of `bitmap`,
+/// counted from the least significant bit of the first byte, is set iff the sibling at height `h`
+/// differs from its default, and only those siblings are listed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparseMerkleProof {
+    pub bitmap: [u8; 32],
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl SparseMerkleTree {
+
+    pub fn new() -> Self {
+        SparseMerkleTree::default()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.node(SPARSE_DEPTH, &[0u8; 32])
+    }
+
+    /// Root of the tree without any occupied leaf.
+    pub fn empty_root() -> [u8; 32] {
+        Self::default_nodes()[SPARSE_DEPTH]
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn get(&self, key: &[u8; 32]) -> Option<&[u8]> {
+        self.leaves.get(key).map(Vec::as_slice)
+    }
+
+    /// Sets the value of `key` and returns the previous one.
+    pub fn insert(&mut self, key: [u8; 32], value: Vec<u8>) -> Option<Vec<u8>> {
+        let previous = self.get(&key).map(<[u8]>::to_vec);
+        self.update_batch(vec![(key, Some(value))]);
+        previous
+    }
+
+    /// Empties the leaf of `key` and returns its value.
+    pub fn remove(&mut self, key: &[u8; 32]) -> Option<Vec<u8>> {
+        let previous = self.get(key).map(<[u8]>::to_vec);
+        self.update_batch(vec![(*key, None)]);
+        previous
+    }
+
+    /// Applies all updates, where `None` empties a leaf, and returns the new root. Every node
+    /// above an updated leaf is hashed once, however many updated leaves lie below it; when a key
+    /// occurs several times, its last update wins.
+    pub fn update_batch(&mut self, updates: Vec<([u8; 32], Option<Vec<u8>>)>) -> [u8; 32] {
+        let mut dirty = BTreeSet::new();
+        for (key, value) in updates {
+            let leaf = match value {
+                Some(value) => {
+                    let leaf = Merkle::hash_leaf(&value);
+                    self.leaves.insert(key, value);
+                    leaf
+                }
+                None => {
+                    self.leaves.remove(&key);
+                    EMPTY_LEAF
+                }
+            };
+            self.set_node(0, key, leaf);
+            dirty.insert(key);
+        }
+
+        for height in 1..=SPARSE_DEPTH {
+            dirty = dirty.into_iter().map(|key| Self::prefix(&key, height)).collect();
+            for key in dirty.iter() {
+                let right = Self::flip(key, height - 1);
+                let parent = Merkle::hash_children(&[self.node(height - 1, key), self.node(height - 1, &right)]);
+                self.set_node(height, *key, parent);
+            }
+        }
+        self.root()
+    }
+
+    /// Returns the authentication path of `key`, which proves inclusion if the key holds a value
+    /// and non-inclusion otherwise.
+    pub fn prove(&self, key: &[u8; 32]) -> SparseMerkleProof {
+        let defaults = Self::default_nodes();
+        let mut bitmap = [0u8; 32];
+        let mut siblings = Vec::new();
+        for height in 0..SPARSE_DEPTH {
+            let sibling = self.node(height, &Self::flip(&Self::prefix(key, height), height));
+            if sibling != defaults[height] {
+                bitmap[height / 8] |= 1 << (height % 8);
+                siblings.push(sibling);
+            }
+        }
+        SparseMerkleProof { bitmap, siblings }
+    }
+
+    /// Checks that `key` holds `value` in the tree with the given root.
+    pub fn verify_inclusion(root: [u8; 32], key: &[u8; 32], value: &[u8], proof: &SparseMerkleProof) -> bool {
+        Self::verify_(root, key, Merkle::hash_leaf(value), proof)
+    }
+
+    /// Checks that `key` holds no value in the tree with the given root.
+    pub fn verify_non_inclusion(root: [u8; 32], key: &[u8; 32], proof: &SparseMerkleProof) -> bool {
+        Self::verify_(root, key, EMPTY_LEAF, proof)
+    }
+
+    fn verify_(root: [u8; 32], key: &[u8; 32], leaf: [u8; 32], proof: &SparseMerkleProof) -> bool {
+        let num_siblings = proof.bitmap.iter().map(|byte| byte.count_ones() as usize).sum::<usize>();
+        if num_siblings != proof.siblings.len() {
+            return false;
+        }
+
+        let defaults = Self::default_nodes();
+        let mut siblings = proof.siblings.iter();
+        let mut node = leaf;
+        for (height, &default) in defaults[..SPARSE_DEPTH].iter().enumerate() {
+            let sibling = if proof.bitmap[height / 8] >> (height % 8) & 1 == 1 {
+                *siblings.next().unwrap()
+            } else {
+                default
+            };
+            node = if Self::bit(key, height) == 0 {
+                Merkle::hash_children(&[node, sibling])
+            } else {
+                Merkle::hash_children(&[sibling, node])
+            };
+        }
+        node == root
+    }
+
+    // NODES
+    // --------------------------------------------------------------------------------------------
+
+    // default hash of a subtree of every height, from an empty leaf up to the empty root
+    fn default_nodes() -> &'static [[u8; 32]] {
+        static DEFAULT_NODES: OnceLock<Vec<[u8; 32]>> = OnceLock::new();
+        DEFAULT_NODES.get_or_init(|| {
+            let mut nodes = vec![EMPTY_LEAF];
+            for height in 0..SPARSE_DEPTH {
+                nodes.push(Merkle::hash_children(&[nodes[height], nodes[height]]));
+            }
+            nodes
+        })
+    }
+
+    // `key` is expected to be a prefix of the given height
+    fn node(&self, height: usize, key: &[u8; 32]) -> [u8; 32] {
+        self.nodes.get(&(height, *key)).copied().unwrap_or(Self::default_nodes()[height])
+    }
+
+    fn set_node(&mut self, height: usize, key: [u8; 32], node: [u8; 32]) {
+        if node == Self::default_nodes()[height] {
+            self.nodes.remove(&(height, key));
+        } else {
+            self.nodes.insert((height, key), node);
+        }
+    }
+
+    // the bit of `key` choosing between the two children at height `height + 1`
+    fn bit(key: &[u8; 32], height: usize) -> u8 {
+        let i = SPARSE_DEPTH - 1 - height;
+        key[i / 8] >> (7 - i % 8) & 1
+    }
+
+    fn flip(key: &[u8; 32], height: usize) -> [u8; 32] {
+        let i = SPARSE_DEPTH - 1 - height;
+        let mut flipped = *key;
+        flipped[i / 8] ^= 1 << (7 - i % 8);
+        flipped
+    }
+
+    // clears the `height` lowest bits of `key`, which identifies its ancestor at that height
+    fn prefix(key: &[u8; 32], height: usize) -> [u8; 32] {
+        let mut prefix = *key;
+        for i in (SPARSE_DEPTH - height)..SPARSE_DEPTH {
+            prefix[i / 8] &= !(1 << (7 - i % 8));
+        }
+        prefix
+    }
+}
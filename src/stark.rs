@@ -0,0 +1,566 @@
+use blake2::{Blake2s256, Digest};
+use rand_core::RngCore;
+use crate::math::{ConstraintProgram, FieldElement, MPolynomial, Polynomial, Zerofier, M};
+use crate::combination::NonlinearCombination;
+use crate::deep::{DeepComposition, OodFrame};
+use crate::fri::Fri;
+use crate::merkle_tree::RowMerkleTree;
+use crate::proof::{Opening, StarkProof};
+use crate::proof_stream::ProofStream;
+use crate::security::SecurityLevel;
+
+// STARK
+// ================================================================================================
+
+/// Parameters of a STARK over an execution trace of `num_registers` columns and
+/// `original_trace_length` rows, together with the domains and degree bounds derived from them.
+///
+/// In zero-knowledge mode (`zk == true`) the prover appends one random row to the trace for every
+/// value of a trace polynomial the verifier gets to see, and mixes a random polynomial of maximal
+/// degree into the nonlinear combination, so that the values opened during FRI and the DEEP
+/// frame are independent of the witness; see `count_randomizers`. The randomized trace is what
+/// determines the trace degree, and hence the quotient degree bounds and the FRI domain; with
+/// `zk == false` no rows are added.
+pub struct Stark {
+    pub expansion_factor: usize,
+    pub num_colinearity_checks: usize,
+    pub target_security_bits: usize,
+    pub num_registers: usize,
+    pub original_trace_length: usize,
+    pub transition_constraints_degree: usize,
+    pub folding_factor: usize,
+    pub num_randomizers: usize,
+    pub randomized_trace_length: usize,
+    pub omicron_domain_length: usize,
+    pub fri_domain_length: usize,
+    pub generator: FieldElement,
+    pub omega: FieldElement,
+    pub omicron: FieldElement,
+    pub zk: bool,
+}
+
+impl Stark {
+
+    pub fn new(expansion_factor: usize, num_colinearity_checks: usize, target_security_bits: usize,
+        num_registers: usize, num_cycles: usize, transition_constraints_degree: usize, zk: bool)
+        -> Self
+    {
+        assert!((128 - M.leading_zeros()) as usize >= target_security_bits,
+            "p must have at least as many bits as security level");
+        assert!(expansion_factor >= 4, "expansion factor must be 4 or greater");
+        assert!(expansion_factor & (expansion_factor - 1) == 0, "expansion factor must be a power of 2");
+        assert!(num_colinearity_checks * 2 >= target_security_bits,
+            "number of colinearity checks must be at least half of security level");
+
+        let mut stark = Stark {
+            expansion_factor,
+            num_colinearity_checks,
+            target_security_bits,
+            num_registers,
+            original_trace_length: num_cycles,
+            transition_constraints_degree,
+            folding_factor: 2,
+            num_randomizers: 0,
+            randomized_trace_length: 0,
+            omicron_domain_length: 0,
+            fri_domain_length: 0,
+            generator: FieldElement::GENERATOR,
+            omega: FieldElement::ONE,
+            omicron: FieldElement::ONE,
+            zk,
+        };
+        stark.derive_lengths();
+        stark
+    }
+
+    /// Sets the folding factor of the FRI run. Every query then opens a larger coset of the
+    /// first layer, so in zk mode the number of random rows, and with it the domains, grow.
+    pub fn with_folding_factor(mut self, folding_factor: usize) -> Self {
+        assert!(matches!(folding_factor, 2 | 4 | 8 | 16), "folding factor must be 2, 4, 8 or 16");
+        self.folding_factor = folding_factor;
+        self.derive_lengths();
+        self
+    }
+
+    /// Number of random rows needed to hide the witness: one per value of a trace polynomial
+    /// revealed to the verifier. Every colinearity check opens the `folding_factor` positions of
+    /// a coset of the first FRI layer, both at `x` and at `omicron * x`, and the DEEP frame adds
+    /// the values at `z` and `omicron * z`.
+    pub fn count_randomizers(num_colinearity_checks: usize, folding_factor: usize) -> usize {
+        2 * folding_factor * num_colinearity_checks + 2
+    }
+
+    fn derive_lengths(&mut self) {
+        self.num_randomizers = if self.zk {
+            Self::count_randomizers(self.num_colinearity_checks, self.folding_factor)
+        } else {
+            0
+        };
+        self.randomized_trace_length = self.original_trace_length + self.num_randomizers;
+        self.omicron_domain_length = (self.randomized_trace_length * self.transition_constraints_degree)
+            .next_power_of_two();
+        self.fri_domain_length = self.omicron_domain_length * self.expansion_factor;
+        self.omega = FieldElement::ZERO.primitive_nth_root(self.fri_domain_length as u128);
+        self.omicron = FieldElement::ZERO.primitive_nth_root(self.omicron_domain_length as u128);
+    }
+
+    pub fn fri(&self) -> Fri {
+        Fri::new(self.generator.as_int(), self.omega.as_int(), self.fri_domain_length as u128,
+            self.expansion_factor as u128, self.num_colinearity_checks as u128)
+            .with_folding_factor(self.folding_factor as u128)
+    }
+
+    /// Security level of the whole proof: the minimum of the low degree test over the evaluation
+    /// domain and the soundness of the nonlinear combination and the out-of-domain check, whose
+    /// polynomials have degree below `omicron_domain_length`; see the `security` module.
+    pub fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::of_stark(&self.fri(), self.omicron_domain_length as u128)
+    }
+
+    pub fn omicron_domain(&self) -> Vec<FieldElement> {
+        (0..self.omicron_domain_length)
+            .map(|i| self.omicron ^ (i as u128))
+            .collect()
+    }
+
+    // DEGREE BOUNDS
+    // --------------------------------------------------------------------------------------------
+
+    /// Degree of the interpolated trace polynomials, including the random rows in zk mode.
+    pub fn trace_degree(&self) -> usize {
+        self.randomized_trace_length - 1
+    }
+
+    /// Returns, for every transition constraint over the variables `[X, current, next]`, the
+    /// degree of the univariate polynomial obtained by substituting the trace polynomials.
+    pub fn transition_degree_bounds(&self, transition_constraints: &[MPolynomial])
+        -> Vec<usize>
+    {
+        let mut point_degrees = vec![1];
+        point_degrees.append(&mut vec![self.trace_degree(); 2 * self.num_registers]);
+        transition_constraints.iter()
+            .map(|constraint| constraint.terms().keys()
+                .map(|exponents| exponents.iter().zip(point_degrees.iter())
+                    .map(|(&e, &d)| e as usize * d)
+                    .sum())
+                .max()
+                .unwrap_or(0))
+            .collect()
+    }
+
+    pub fn transition_quotient_degree_bounds(&self,
+        transition_constraints: &[MPolynomial]) -> Vec<usize>
+    {
+        self.transition_degree_bounds(transition_constraints).iter()
+            .map(|d| d.saturating_sub(self.original_trace_length - 1))
+            .collect()
+    }
+
+    /// Returns the largest degree bound of the transition quotients, rounded up to one less than
+    /// a power of two.
+    pub fn max_degree(&self, transition_constraints: &[MPolynomial]) -> usize {
+        let max_degree = self.transition_quotient_degree_bounds(transition_constraints).into_iter()
+            .max()
+            .unwrap_or(0);
+        (max_degree + 1).next_power_of_two() - 1
+    }
+
+    /// Returns the degree bounds of the boundary quotients, one per register; a boundary
+    /// constraint is given as `(cycle, register, value)`.
+    pub fn boundary_quotient_degree_bounds(&self, boundary: &[(usize, usize, FieldElement)])
+        -> Vec<usize>
+    {
+        (0..self.num_registers)
+            .map(|register| {
+                let num_points = boundary.iter().filter(|(_, r, _)| *r == register).count();
+                self.trace_degree() - num_points
+            })
+            .collect()
+    }
+
+    /// Returns the degree bounds of all polynomials entering the nonlinear combination: the
+    /// randomizer polynomial in zk mode, then the transition quotients, then the boundary
+    /// quotients.
+    pub fn combination_degree_bounds(&self, transition_constraints: &[MPolynomial],
+        boundary: &[(usize, usize, FieldElement)]) -> Vec<usize>
+    {
+        let mut degree_bounds = Vec::new();
+        if self.zk {
+            degree_bounds.push(self.fri().max_degree());
+        }
+        degree_bounds.append(&mut self.transition_quotient_degree_bounds(transition_constraints));
+        degree_bounds.append(&mut self.boundary_quotient_degree_bounds(boundary));
+        degree_bounds
+    }
+
+    /// Returns the nonlinear combination of the randomizer and quotients with weights drawn from
+    /// the transcript randomness. Its degree bound is that of the FRI run, unless a quotient
+    /// exceeds it.
+    pub fn combination(&self, transition_constraints: &[MPolynomial],
+        boundary: &[(usize, usize, FieldElement)], randomness: &[u8]) -> NonlinearCombination
+    {
+        let degree_bounds = self.combination_degree_bounds(transition_constraints, boundary);
+        let max_degree = degree_bounds.iter()
+            .fold(self.fri().max_degree(), |acc, &d| acc.max(d));
+        let max_degree = (max_degree + 1).next_power_of_two() - 1;
+        NonlinearCombination::from_randomness(max_degree, &degree_bounds, randomness)
+    }
+
+    // ZEROFIERS AND OUT-OF-DOMAIN CHECKS
+    // --------------------------------------------------------------------------------------------
+
+    /// Returns the zerofier of the rows on which the transition constraints apply, i.e. the
+    /// first `original_trace_length - 1` points of the omicron domain.
+    ///
+    /// Unless the trace fills most of the omicron domain, nearly all of its points are excluded,
+    /// so the zerofier is not sparse. It is evaluated through its `original_trace_length - 1`
+    /// linear factors: every point evaluation, once at `z` and once per opened position in the
+    /// verifier, costs time linear in the trace length rather than logarithmic. Over the FRI
+    /// domain the prover builds the dense polynomial with a product tree and evaluates it with
+    /// an NTT.
+    pub fn transition_zerofier(&self) -> Zerofier {
+        Zerofier::excluding_last(self.omicron, self.omicron_domain_length,
+            self.omicron_domain_length - (self.original_trace_length - 1))
+    }
+
+    /// Recomputes the composition polynomial at the out-of-domain point `z` from the trace
+    /// values in `frame`, for the verifier to compare with `frame.composition`. In zk mode the
+    /// value of the randomizer polynomial at `z` must be supplied as well.
+    pub fn ood_composition_value(&self, transition_constraints: &[MPolynomial],
+        boundary: &[(usize, usize, FieldElement)], combination: &NonlinearCombination,
+        z: FieldElement, frame: &OodFrame, randomizer_value: Option<FieldElement>) -> FieldElement
+    {
+        assert!(randomizer_value.is_some() == self.zk, "randomizer value must be given in zk mode only");
+        let mut values = Vec::new();
+        values.extend(randomizer_value);
+
+        let mut point = vec![z];
+        point.extend_from_slice(&frame.current);
+        point.extend_from_slice(&frame.next);
+        let transition_zerofier = self.transition_zerofier().evaluate(z);
+        for constraint in transition_constraints {
+            values.push(constraint.evaluate(&point) / transition_zerofier);
+        }
+
+        for (register, (interpolant, zerofier)) in self.boundary_polynomials(boundary).iter().enumerate() {
+            values.push((frame.current[register] - interpolant.evaluate(z)) / zerofier.evaluate(z));
+        }
+
+        combination.evaluate(z, &values)
+    }
+
+    /// Returns, for every register, the interpolant of its boundary values and the zerofier of
+    /// the cycles they apply to; the boundary quotient is `(trace - interpolant) / zerofier`.
+    pub fn boundary_polynomials(&self, boundary: &[(usize, usize, FieldElement)])
+        -> Vec<(Polynomial, Polynomial)>
+    {
+        (0..self.num_registers)
+            .map(|register| {
+                let (domain, values): (Vec<FieldElement>, Vec<FieldElement>) = boundary.iter()
+                    .filter(|(_, r, _)| *r == register)
+                    .map(|&(cycle, _, value)| (self.omicron ^ (cycle as u128), value))
+                    .unzip();
+                let interpolant = if domain.is_empty() {
+                    Polynomial::zero()
+                } else {
+                    Polynomial::interpolate_domain(&domain, &values)
+                };
+                (interpolant, Polynomial::zerofier_domain(&domain))
+            })
+            .collect()
+    }
+
+    // CONSTRAINT EVALUATION
+    // --------------------------------------------------------------------------------------------
+
+    /// Compiles the transition constraints over the variables `[X, current, next]` into one
+    /// program, to be evaluated at every point of the FRI domain.
+    pub fn transition_program(&self, transition_constraints: &[MPolynomial]) -> ConstraintProgram {
+        ConstraintProgram::compile(transition_constraints, 1 + 2 * self.num_registers)
+    }
+
+    /// Returns the codewords of the transition quotients over the FRI domain, given the codewords
+    /// of the trace polynomials over that domain. The next row of a point is `omicron` times it,
+    /// i.e. `expansion_factor` positions further in the domain.
+    pub fn transition_quotient_codewords(&self, program: &ConstraintProgram,
+        trace_codewords: &[Vec<FieldElement>]) -> Vec<Vec<FieldElement>>
+    {
+        assert!(trace_codewords.len() == self.num_registers, "number of trace codewords does not match");
+        assert!(trace_codewords.iter().all(|c| c.len() == self.fri_domain_length),
+            "codeword length does not match the FRI domain");
+        assert!(program.num_inputs() == 1 + 2 * self.num_registers, "program does not take [X, current, next]");
+
+        let length = self.fri_domain_length;
+        let mut x = self.generator;
+        let mut rows = Vec::with_capacity(length);
+        for i in 0..length {
+            let next = (i + self.expansion_factor) % length;
+            let mut row = Vec::with_capacity(program.num_inputs());
+            row.push(x);
+            row.extend(trace_codewords.iter().map(|codeword| codeword[i]));
+            row.extend(trace_codewords.iter().map(|codeword| codeword[next]));
+            rows.push(row);
+            x = x * self.omega;
+        }
+        let values = program.evaluate_rows(&rows);
+
+        let zerofier = self.transition_zerofier().evaluate_coset(self.generator, self.omega, length);
+        let zerofier_inverses = FieldElement::batch_inverse(&zerofier);
+        (0..program.num_outputs())
+            .map(|c| values.iter().zip(zerofier_inverses.iter()).map(|(v, &z)| v[c] * z).collect())
+            .collect()
+    }
+
+    // RANDOMIZATION
+    // --------------------------------------------------------------------------------------------
+
+    /// Appends `num_randomizers` rows of uniformly random field elements to the trace. The trace
+    /// is returned unchanged when zero-knowledge is disabled.
+    pub fn randomize_trace<R: RngCore>(&self, trace: &[Vec<FieldElement>], rng: &mut R)
+        -> Vec<Vec<FieldElement>>
+    {
+        assert!(trace.len() == self.original_trace_length, "trace length does not match");
+        let mut randomized_trace = trace.to_vec();
+        for _ in 0..self.num_randomizers {
+            randomized_trace.push((0..self.num_registers).map(|_| Self::random_element(rng)).collect());
+        }
+        randomized_trace
+    }
+
+    /// Returns a uniformly random polynomial of degree at most `fri().max_degree()` to be
+    /// committed and added to the nonlinear combination, or `None` when zero-knowledge is
+    /// disabled. A randomizer of full degree masks every coefficient of the combination.
+    pub fn randomizer_polynomial<R: RngCore>(&self, rng: &mut R) -> Option<Polynomial> {
+        if !self.zk {
+            return None;
+        }
+        let coefficients = (0..=self.fri().max_degree()).map(|_| Self::random_element(rng)).collect();
+        Some(Polynomial::new(coefficients))
+    }
+
+    // uniform by rejection sampling: 128 random bits are kept only if they are below M, which
+    // happens with probability M / 2^128 > 3/4
+    fn random_element<R: RngCore>(rng: &mut R) -> FieldElement {
+        loop {
+            let mut bytes = [0u8; 16];
+            rng.fill_bytes(&mut bytes);
+            let value = u128::from_le_bytes(bytes);
+            if value < M {
+                return FieldElement::new(value);
+            }
+        }
+    }
+
+    // PROVER
+    // --------------------------------------------------------------------------------------------
+
+    /// Proves that `trace` satisfies the transition constraints over `[X, current, next]` and the
+    /// boundary constraints `(cycle, register, value)`, writing the proof into `proof_stream` in
+    /// the layout of `StarkProof`.
+    ///
+    /// The trace and, in zk mode, the randomizer polynomial are committed over the FRI domain,
+    /// followed by every quotient. The composition polynomial, i.e. their nonlinear combination,
+    /// is opened at the out-of-domain point `z`, and FRI is run on the DEEP composition of the
+    /// trace and the composition polynomial. Finally the commitments are opened at every position
+    /// of the cosets queried in the first FRI layer.
+    pub fn prove<R: RngCore>(&self, trace: &[Vec<FieldElement>], transition_constraints: &[MPolynomial],
+        boundary: &[(usize, usize, FieldElement)], rng: &mut R, proof_stream: &mut ProofStream)
+    {
+        let fri = self.fri();
+        assert!(fri.num_rounds() >= 2, "FRI needs at least 2 rounds to open the trace");
+        assert!(trace.iter().all(|row| row.len() == self.num_registers), "trace width does not match");
+
+        // commit to the randomized trace
+        let trace = self.randomize_trace(trace, rng);
+        let trace_domain = (0..self.randomized_trace_length)
+            .map(|i| self.omicron ^ (i as u128))
+            .collect::<Vec<FieldElement>>();
+        let trace_polynomials = (0..self.num_registers)
+            .map(|r| Polynomial::interpolate_domain(&trace_domain,
+                &trace.iter().map(|row| row[r]).collect::<Vec<FieldElement>>()))
+            .collect::<Vec<Polynomial>>();
+        let trace_codewords = trace_polynomials.iter()
+            .map(|p| p.evaluate_coset(self.generator, self.omega, self.fri_domain_length))
+            .collect::<Vec<Vec<FieldElement>>>();
+        let trace_tree = RowMerkleTree::from_columns(&trace_codewords);
+        proof_stream.push(trace_tree.root().to_vec());
+
+        // commit to the randomizer, then to every quotient
+        let randomizer = self.randomizer_polynomial(rng);
+        let mut codewords = Vec::new();
+        if let Some(randomizer) = &randomizer {
+            codewords.push(randomizer.evaluate_coset(self.generator, self.omega, self.fri_domain_length));
+        }
+        let program = self.transition_program(transition_constraints);
+        codewords.append(&mut self.transition_quotient_codewords(&program, &trace_codewords));
+        let boundary_polynomials = self.boundary_polynomials(boundary);
+        for (polynomial, (interpolant, zerofier)) in trace_polynomials.iter().zip(boundary_polynomials) {
+            let (quotient, remainder) = (polynomial - interpolant).divide(&zerofier).unwrap();
+            assert!(remainder.is_zero(), "trace does not satisfy the boundary constraints");
+            codewords.push(quotient.evaluate_coset(self.generator, self.omega, self.fri_domain_length));
+        }
+        let trees = codewords.iter()
+            .map(|codeword| RowMerkleTree::from_columns(std::slice::from_ref(codeword)))
+            .collect::<Vec<RowMerkleTree>>();
+        for tree in trees.iter() {
+            proof_stream.push(tree.root().to_vec());
+        }
+
+        // open the composition polynomial and the trace out of domain
+        let randomness = proof_stream.prover_fiat_shamir();
+        let combination = self.combination(transition_constraints, boundary, &randomness);
+        assert!(combination.max_degree == fri.max_degree(), "transition constraints exceed the declared degree");
+        let domain = fri.eval_domain();
+        let composition_codeword = combination.combine_codewords(&codewords, &domain);
+        let composition = Polynomial::interpolate_coset(&composition_codeword, self.generator, self.omega);
+
+        let z = self.ood_point(&randomness);
+        let frame = OodFrame {
+            current: trace_polynomials.iter().map(|p| p.evaluate(z)).collect(),
+            next: trace_polynomials.iter().map(|p| p.evaluate(self.omicron * z)).collect(),
+            composition: composition.evaluate(z),
+        };
+        proof_stream.push(frame.to_bytes());
+        if let Some(randomizer) = &randomizer {
+            proof_stream.push(FieldElement::elements_as_bytes(&[randomizer.evaluate(z)]).to_vec());
+        }
+
+        // prove the DEEP composition low degree
+        let deep = DeepComposition::new(z, self.omicron, self.num_registers, self.trace_degree(),
+            combination.max_degree, &proof_stream.prover_fiat_shamir());
+        let deep_codeword = deep.composition_codeword(&domain, &trace_codewords, &composition_codeword, &frame);
+        let indices = fri.prove(&deep_codeword, proof_stream);
+
+        // open the commitments at the queried cosets
+        let positions = self.opened_positions(&indices);
+        let next = |position: usize| (position + self.expansion_factor) % self.fri_domain_length;
+        let trace_rows = positions.iter().map(|&p| trace_tree.open(p)).collect::<Vec<Opening>>();
+        let next_trace_rows = positions.iter().map(|&p| trace_tree.open(next(p))).collect::<Vec<Opening>>();
+        Opening::write_many(&trace_rows, proof_stream);
+        Opening::write_many(&next_trace_rows, proof_stream);
+        for tree in trees.iter() {
+            let rows = positions.iter().map(|&p| tree.open(p)).collect::<Vec<Opening>>();
+            Opening::write_many(&rows, proof_stream);
+        }
+    }
+
+    // the combination weights are drawn from the same transcript state, so the out-of-domain
+    // point is sampled from a seed of its own
+    fn ood_point(&self, randomness: &[u8]) -> FieldElement {
+        let seed = Blake2s256::new().chain_update(randomness).chain_update(b"ood").finalize();
+        DeepComposition::sample_point(&seed, self.omicron_domain_length, self.generator,
+            self.fri_domain_length)
+    }
+
+    // positions of the FRI domain in the cosets of the first layer queried at `indices`
+    fn opened_positions(&self, indices: &[usize]) -> Vec<usize> {
+        let num_leafs = self.fri_domain_length / self.folding_factor;
+        indices.iter()
+            .flat_map(|&index| (0..self.folding_factor).map(move |t| index + t * num_leafs))
+            .collect()
+    }
+
+    // VERIFIER
+    // --------------------------------------------------------------------------------------------
+
+    /// Verifies a proof written by `prove` for the same constraints.
+    ///
+    /// The verifier checks the composition value in the out-of-domain frame against the
+    /// constraints, runs FRI on the DEEP composition, and at every opened position checks the
+    /// Merkle openings, the quotients against the constraints, and the value of the DEEP
+    /// composition against the first FRI layer.
+    pub fn verify(&self, proof_stream: &mut ProofStream, transition_constraints: &[MPolynomial],
+        boundary: &[(usize, usize, FieldElement)]) -> bool
+    {
+        let fri = self.fri();
+        if fri.num_rounds() < 2 {
+            return false;
+        }
+        let num_quotients = transition_constraints.len() + self.num_registers;
+        let start = proof_stream.read_index;
+        let proof = match StarkProof::read(self, num_quotients, proof_stream) {
+            Ok(proof) => proof,
+            Err(_) => return false,
+        };
+
+        // replay the transcript up to the commitments and check the out-of-domain frame
+        let num_roots = 1 + proof.randomizer_root.iter().count() + num_quotients;
+        let mut transcript = ProofStream { objects: proof_stream.objects.clone(), read_index: start + num_roots };
+        let randomness = transcript.verifier_fiat_shamir();
+        let combination = self.combination(transition_constraints, boundary, &randomness);
+        if combination.max_degree != fri.max_degree() {
+            return false;
+        }
+        let z = self.ood_point(&randomness);
+        let frame = &proof.ood_frame;
+        if self.ood_composition_value(transition_constraints, boundary, &combination, z, frame,
+            proof.ood_randomizer_value) != frame.composition
+        {
+            return false;
+        }
+
+        // verify the low degree of the DEEP composition
+        transcript.read_index += 1 + proof.ood_randomizer_value.iter().count();
+        let deep = DeepComposition::new(z, self.omicron, self.num_registers, self.trace_degree(),
+            combination.max_degree, &transcript.verifier_fiat_shamir());
+        let mut polynomial_values = Vec::new();
+        if !fri.verify(&mut transcript, &mut polynomial_values)
+            || polynomial_values.len() != proof.trace_rows.len()
+        {
+            return false;
+        }
+
+        // check the openings at every position of the queried cosets
+        let mut commitments = Vec::with_capacity(combination.degree_bounds.len());
+        if let Some(root) = proof.randomizer_root {
+            commitments.push((root, proof.randomizer_rows.as_slice()));
+        }
+        for (root, rows) in proof.quotient_roots.iter().zip(proof.quotient_rows.iter()) {
+            commitments.push((*root, rows.as_slice()));
+        }
+        let program = self.transition_program(transition_constraints);
+        let transition_zerofier = self.transition_zerofier();
+        let boundary_polynomials = self.boundary_polynomials(boundary);
+        let num_transition_quotients = program.num_outputs();
+        for (s, &(position, value)) in polynomial_values.iter().enumerate() {
+            let next_position = (position + self.expansion_factor) % self.fri_domain_length;
+            let (row, next_row) = (&proof.trace_rows[s], &proof.next_trace_rows[s]);
+            if !RowMerkleTree::verify(self.fri_domain_length, proof.trace_root, position, row)
+                || !RowMerkleTree::verify(self.fri_domain_length, proof.trace_root, next_position, next_row)
+            {
+                return false;
+            }
+            let mut values = Vec::with_capacity(combination.degree_bounds.len());
+            for &(root, rows) in commitments.iter() {
+                if !RowMerkleTree::verify(self.fri_domain_length, root, position, &rows[s]) {
+                    return false;
+                }
+                values.push(rows[s].values[0]);
+            }
+            let quotients = &values[values.len() - num_quotients..];
+
+            let x = self.generator * (self.omega ^ (position as u128));
+            let mut point = vec![x];
+            point.extend_from_slice(&row.values);
+            point.extend_from_slice(&next_row.values);
+            let zerofier = transition_zerofier.evaluate(x);
+            for (constraint, &quotient) in program.evaluate(&point).into_iter().zip(quotients) {
+                if constraint != quotient * zerofier {
+                    return false;
+                }
+            }
+            for (register, (interpolant, zerofier)) in boundary_polynomials.iter().enumerate() {
+                let quotient = quotients[num_transition_quotients + register];
+                if row.values[register] - interpolant.evaluate(x) != quotient * zerofier.evaluate(x) {
+                    return false;
+                }
+            }
+
+            let composition_value = combination.evaluate(x, &values);
+            if deep.composition_value(x, &row.values, composition_value, frame) != value {
+                return false;
+            }
+        }
+        true
+    }
+}
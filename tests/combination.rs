@@ -0,0 +1,37 @@
+use stark_anatomy::{FieldElement, NonlinearCombination, Polynomial};
+
+mod common;
+use common::fe;
+
+// polynomial of the given degree with all coefficients non-zero
+fn polynomial(degree: usize) -> Polynomial {
+    Polynomial::new((0..=degree as u128).map(|i| fe(3 * i + 1)).collect())
+}
+
+#[test]
+fn combination_stays_within_max_degree() {
+    let combination = NonlinearCombination::from_randomness(15, &[3, 9, 15], b"weights");
+    let inputs = [polynomial(3), polynomial(9), polynomial(15)];
+    assert_eq!(combination.combine_polynomials(&inputs).degree(), Some(15));
+
+    // the combined codeword is the codeword of the combined polynomial
+    let omega = FieldElement::ZERO.primitive_nth_root(64);
+    let codewords = inputs.iter()
+        .map(|p| p.evaluate_coset(FieldElement::GENERATOR, omega, 64))
+        .collect::<Vec<_>>();
+    let domain = (0..64).map(|i| FieldElement::GENERATOR * (omega ^ i)).collect::<Vec<_>>();
+    let combined = combination.combine_codewords(&codewords, &domain);
+    assert_eq!(Polynomial::interpolate_coset(&combined, FieldElement::GENERATOR, omega),
+        combination.combine_polynomials(&inputs));
+}
+
+#[test]
+fn quotient_one_over_its_bound_is_caught() {
+    // the second input exceeds its bound of 9 by one; unshifted it would still be far below the
+    // max degree, but its shifted copy lifts it above
+    for seed in 0..8u8 {
+        let combination = NonlinearCombination::from_randomness(15, &[3, 9, 15], &[seed]);
+        let inputs = [polynomial(3), polynomial(10), polynomial(15)];
+        assert_eq!(combination.combine_polynomials(&inputs).degree(), Some(16));
+    }
+}
@@ -0,0 +1,36 @@
+// Helpers shared by the integration tests. Every test crate compiles this module on its own and
+// uses only part of it.
+#![allow(dead_code)]
+
+use rand_core::RngCore;
+use stark_anatomy::FieldElement;
+
+pub fn fe(value: u128) -> FieldElement {
+    FieldElement::new(value)
+}
+
+// deterministic stand-in for a random number generator, for reproducible salts and randomizers
+pub struct CounterRng(pub u64);
+
+impl RngCore for CounterRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
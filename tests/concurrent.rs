@@ -0,0 +1,81 @@
+// Every result computed in parallel with the `concurrent` feature must be bit-identical to the
+// serial computation. These tests compare against serial reference implementations and against
+// fixed digests, and pass both with and without the feature.
+
+use blake2::{Blake2s256, Digest};
+use stark_anatomy::{ConstraintProgram, FieldElement, FriOptions, MPolynomial, Merkle, MerkleTree, Polynomial,
+    ProofStream, RowMerkleTree, SaltedMerkleTree};
+use stark_anatomy::merkle_tree::SALT_BYTES;
+
+mod common;
+use common::fe;
+
+fn polynomial(degree: usize) -> Polynomial {
+    Polynomial::new((0..=degree as u128).map(|i| fe(i * i * 13 + 7 * i + 1)).collect())
+}
+
+// root of a binary tree over the given leaf hashes, hashed level by level on one thread
+fn serial_root(leafs: Vec<[u8; 32]>) -> [u8; 32] {
+    let mut level = leafs;
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| Blake2s256::new().chain_update([1]).chain_update(pair.concat()).finalize().into())
+            .collect();
+    }
+    level[0]
+}
+
+#[test]
+fn coset_evaluation_matches_horner() {
+    let offset = FieldElement::GENERATOR;
+    let omega = FieldElement::ZERO.primitive_nth_root(1024);
+    let polynomial = polynomial(300);
+    let domain = (0..1024).map(|i| offset * (omega ^ i)).collect::<Vec<_>>();
+    let expected = domain.iter().map(|&x| polynomial.evaluate(x)).collect::<Vec<_>>();
+
+    assert_eq!(polynomial.evaluate_coset(offset, omega, 1024), expected);
+    assert_eq!(polynomial.evaluate_domain(&domain), expected);
+    assert_eq!(Polynomial::interpolate_coset(&expected, offset, omega), polynomial);
+}
+
+#[test]
+fn merkle_roots_match_serial_hashing() {
+    let data_array = (0..512u64).map(|i| (i * i).to_le_bytes().to_vec()).collect::<Vec<_>>();
+    let leafs = data_array.iter().map(|data| Merkle::hash_leaf(data)).collect::<Vec<_>>();
+    assert_eq!(MerkleTree::new(&data_array).root(), serial_root(leafs));
+
+    let rows = (0..256u128).map(|i| vec![fe(i), fe(3 * i + 1)]).collect::<Vec<_>>();
+    let leafs = rows.iter().map(|row| Merkle::hash_row(row)).collect::<Vec<_>>();
+    assert_eq!(RowMerkleTree::from_rows(rows).root(), serial_root(leafs));
+
+    let salts = (0..512u32).map(|i| [i as u8; SALT_BYTES]).collect::<Vec<_>>();
+    let leafs = salts.iter().zip(data_array.iter())
+        .map(|(salt, data)| Merkle::hash_salted(salt, data))
+        .collect::<Vec<_>>();
+    assert_eq!(SaltedMerkleTree::from_salts(&data_array, salts, 2).root(), serial_root(leafs));
+}
+
+#[test]
+fn constraint_rows_match_row_by_row_evaluation() {
+    let x = MPolynomial::variables(3);
+    let constraints = vec![&x[2] - (&x[1] ^ 3) - &x[0], &x[1] * &x[2] + MPolynomial::constant(fe(5))];
+    let program = ConstraintProgram::compile(&constraints, 3);
+    let rows = (0..300u128).map(|i| vec![fe(i), fe(i * 7 + 2), fe(i * i)]).collect::<Vec<_>>();
+    let expected = rows.iter().map(|row| program.evaluate(row)).collect::<Vec<_>>();
+    assert_eq!(program.evaluate_rows(&rows), expected);
+}
+
+#[test]
+fn fri_proof_matches_fixed_digest() {
+    let fri = FriOptions::new(1 << 12, 4, 8).with_folding_factor(4).build().unwrap();
+    let codeword = polynomial(fri.max_degree())
+        .evaluate_coset(FieldElement::new(fri.offset), FieldElement::new(fri.omega), fri.domain_length as usize);
+    let mut proof_stream = ProofStream::new();
+    fri.prove(&codeword, &mut proof_stream);
+    let digest: [u8; 32] = Blake2s256::digest(proof_stream.objects.concat()).into();
+    assert_eq!(hex(&digest), "c8d8ee1854afcba818271daacffb84f50012af581d352f31b5f6772036b8e161");
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,99 @@
+use stark_anatomy::{ConstraintParser, MPolynomial, ParseError, ParseErrorKind, M};
+
+mod common;
+use common::fe;
+
+#[test]
+fn parses_registers_in_stark_layout() {
+    // variables are [X, x0, x1, x2, x0', x1', x2']
+    let parser = ConstraintParser::new(3);
+    let x = MPolynomial::variables(7);
+    let expected = &x[5] - &x[1] * &x[2] + (&x[3] ^ 7) * fe(3);
+    assert_eq!(parser.parse("x1' - x0*x1 + 3*x2^7").unwrap(), expected);
+    assert_eq!(parser.parse("  x1'-x0 *x1+3* x2 ^ 7 ").unwrap(), expected);
+    assert_eq!(parser.parse("X*x2'").unwrap(), &x[0] * &x[6]);
+
+    // constants are reduced modulo M
+    assert_eq!(parser.parse(&(M + 5).to_string()).unwrap(), MPolynomial::constant(fe(5)));
+    assert_eq!(parser.parse("0").unwrap(), MPolynomial::zero());
+}
+
+#[test]
+fn respects_precedence() {
+    let parser = ConstraintParser::new(2);
+    let x = MPolynomial::variables(5);
+    assert_eq!(parser.parse("-x0^2").unwrap(), -(&x[1] ^ 2));
+    assert_eq!(parser.parse("--x0").unwrap(), x[1].clone());
+    assert_eq!(parser.parse("1 - x0 - x1").unwrap(), MPolynomial::constant(fe(1)) - &x[1] - &x[2]);
+    assert_eq!(parser.parse("2*(x0 + x1')^2").unwrap(), ((&x[1] + &x[4]) ^ 2) * fe(2));
+    assert_eq!(parser.parse("x0 * -x1").unwrap(), -(&x[1] * &x[2]));
+}
+
+#[test]
+fn display_round_trips() {
+    let parser = ConstraintParser::new(2);
+    let x = MPolynomial::variables(5);
+    let polynomials = vec![
+        MPolynomial::zero(),
+        MPolynomial::constant(-fe(7)),
+        &x[3] - (&x[1] ^ 3) - &x[0] * fe(5),
+        ((&x[1] + &x[4] - &x[2]) ^ 3) + MPolynomial::constant(fe(M / 2)),
+        -(&x[0] * &x[4]),
+    ];
+    for polynomial in polynomials {
+        let written = parser.display(&polynomial).to_string();
+        assert_eq!(parser.parse(&written).unwrap(), polynomial, "{}", written);
+    }
+    let polynomial = parser.parse("x0' - x0^3 - 5*X").unwrap();
+    assert_eq!(parser.display(&polynomial).to_string(), "x0' - x0^3 - 5*X");
+}
+
+#[test]
+fn reports_error_positions() {
+    let parser = ConstraintParser::new(3);
+    let error = |input: &str| parser.parse(input).unwrap_err();
+    let unexpected = |found: &str, expected: &'static str| ParseErrorKind::UnexpectedToken {
+        found: found.to_string(),
+        expected,
+    };
+
+    let e = error("x0 + * x1");
+    assert_eq!(e.position, 5);
+    assert!(matches!(e.kind, ParseErrorKind::UnexpectedToken { ref found, .. } if found == "*"));
+    assert_eq!(error("x0 x1'"), ParseError {
+        position: 3,
+        kind: unexpected("x1'", "an operator or the end of the input"),
+    });
+    assert_eq!(error("x0^x1"), ParseError { position: 3, kind: unexpected("x1", "an exponent") });
+    assert_eq!(error("x0 + y").kind, unexpected("y", "a number, a register, `X`, `-` or `(`"));
+    assert_eq!(error("2 * x3'"), ParseError { position: 4, kind: ParseErrorKind::UnknownRegister(3) });
+    assert_eq!(error("x0^1000000000000000000000000000000000000000").kind, ParseErrorKind::ExponentTooLarge);
+    assert_eq!(error("(x0 + 1").position, 7);
+    assert!(matches!(error("(x0 + 1").kind, ParseErrorKind::UnexpectedEnd { .. }));
+    assert_eq!(error("").to_string(),
+        "at position 0: unexpected end of input, expected a number, a register, `X`, `-` or `(`");
+}
+
+#[test]
+fn rejects_overflowing_exponents_and_padded_indices() {
+    let parser = ConstraintParser::new(3);
+    let error = |input: &str| parser.parse(input).unwrap_err();
+
+    let max = u128::MAX.to_string();
+    let product = format!("x0^{} * x0", max);
+    assert_eq!(error(&product), ParseError {
+        position: product.find('*').unwrap(),
+        kind: ParseErrorKind::ExponentTooLarge,
+    });
+    let power = format!("(x0^2)^{}", 1u128 << 127);
+    assert_eq!(error(&power), ParseError { position: 7, kind: ParseErrorKind::ExponentTooLarge });
+    assert!(parser.parse(&format!("x0^{} * x1", max)).is_ok());
+
+    for input in ["x01", "x00'", "2 * x002"] {
+        let e = error(input);
+        assert_eq!(e.position, input.find('x').unwrap());
+        assert!(matches!(e.kind, ParseErrorKind::UnexpectedToken { expected, .. }
+            if expected == "a register index without leading zeros"));
+    }
+    assert!(parser.parse("x0 + x0'").is_ok());
+}
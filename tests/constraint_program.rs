@@ -0,0 +1,68 @@
+use stark_anatomy::{ConstraintProgram, MPolynomial, Polynomial, Stark};
+
+mod common;
+use common::fe;
+
+fn constraints() -> Vec<MPolynomial> {
+    let x = MPolynomial::variables(3);
+    vec![
+        &x[2] - (&x[1] ^ 3) - MPolynomial::constant(fe(5)) * &x[0],
+        (&x[1] ^ 3) * &x[2] + MPolynomial::constant(fe(9)),
+        (&x[1] ^ 6) - &x[0] * &x[1],
+        MPolynomial::zero(),
+    ]
+}
+
+#[test]
+fn program_matches_direct_evaluation() {
+    let constraints = constraints();
+    let program = ConstraintProgram::compile(&constraints, 3);
+    assert_eq!(program.num_outputs(), 4);
+
+    let rows = (0..20u128).map(|i| vec![fe(i), fe(i * i + 1), fe(1 << (i + 90))]).collect::<Vec<_>>();
+    let values = program.evaluate_rows(&rows);
+    for (row, values) in rows.iter().zip(values) {
+        let expected = constraints.iter().map(|c| c.evaluate(row)).collect::<Vec<_>>();
+        assert_eq!(values, expected);
+        assert_eq!(program.evaluate(row), expected);
+    }
+}
+
+#[test]
+fn subexpressions_are_shared() {
+    let constraints = constraints();
+    let single = ConstraintProgram::compile(&constraints[..1], 3);
+    let twice = ConstraintProgram::compile(&[constraints[0].clone(), constraints[0].clone()], 3);
+    assert_eq!(twice.instructions(), single.instructions());
+
+    // x1^8 takes three squarings, and x1^3 and x1^6 of the other constraints reuse each other
+    let x = MPolynomial::variables(2);
+    assert_eq!(ConstraintProgram::compile(&[&x[1] ^ 8], 2).num_multiplications(), 3);
+    let all = ConstraintProgram::compile(&constraints, 3);
+    let separately: usize = constraints.iter()
+        .map(|c| ConstraintProgram::compile(std::slice::from_ref(c), 3).num_multiplications())
+        .sum();
+    assert!(all.num_multiplications() < separately);
+}
+
+#[test]
+fn transition_quotients_are_low_degree() {
+    // a single register cubed at every step
+    let stark = Stark::new(4, 2, 2, 1, 8, 3, false);
+    let mut trace = vec![fe(3)];
+    for _ in 1..8 {
+        trace.push(trace.last().copied().unwrap() ^ 3);
+    }
+    let domain = stark.omicron_domain()[..8].to_vec();
+    let trace_polynomial = Polynomial::interpolate_domain(&domain, &trace);
+    let codeword = trace_polynomial.evaluate_coset(stark.generator, stark.omega, stark.fri_domain_length);
+
+    let x = MPolynomial::variables(3);
+    let transition_constraints = vec![&x[2] - (&x[1] ^ 3)];
+    let program = stark.transition_program(&transition_constraints);
+    let quotients = stark.transition_quotient_codewords(&program, &[codeword]);
+
+    let degree_bound = stark.transition_quotient_degree_bounds(&transition_constraints)[0];
+    let quotient = Polynomial::interpolate_coset(&quotients[0], stark.generator, stark.omega);
+    assert_eq!(quotient.degree(), Some(degree_bound));
+}
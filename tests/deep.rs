@@ -0,0 +1,93 @@
+use stark_anatomy::{DeepComposition, FieldElement, MPolynomial, OodFrame, Polynomial, Stark};
+
+mod common;
+use common::fe;
+
+// a one-register trace squaring its value at every step, with the composition polynomial of its
+// transition and boundary quotients
+struct Setup {
+    stark: Stark,
+    constraints: Vec<MPolynomial>,
+    boundary: Vec<(usize, usize, FieldElement)>,
+    trace: Polynomial,
+    composition: Polynomial,
+    deep: DeepComposition,
+}
+
+fn setup() -> Setup {
+    let stark = Stark::new(4, 2, 2, 1, 8, 2, false);
+    let mut values = vec![fe(2)];
+    for _ in 1..8 {
+        values.push(values.last().copied().unwrap() ^ 2);
+    }
+    let trace = Polynomial::interpolate_domain(&stark.omicron_domain()[..8], &values);
+
+    let x = MPolynomial::variables(3);
+    let constraints = vec![&x[2] - (&x[1] ^ 2)];
+    let boundary = vec![(0, 0, fe(2))];
+
+    let point = [Polynomial::monomial(1, FieldElement::ONE), trace.clone(), trace.scale(stark.omicron)];
+    let transition = constraints[0].evaluate_symbolic(&point);
+    let (transition_quotient, remainder) = transition.divide(&stark.transition_zerofier().polynomial()).unwrap();
+    assert!(remainder.is_zero());
+    let boundary_zerofier = Polynomial::zerofier_domain(&[FieldElement::ONE]);
+    let (boundary_quotient, remainder) = (&trace - Polynomial::constant(fe(2))).divide(&boundary_zerofier).unwrap();
+    assert!(remainder.is_zero());
+
+    let combination = stark.combination(&constraints, &boundary, b"combination");
+    let composition = combination.combine_polynomials(&[transition_quotient, boundary_quotient]);
+    let z = DeepComposition::sample_point(b"z", stark.omicron_domain_length, stark.generator,
+        stark.fri_domain_length);
+    let deep = DeepComposition::new(z, stark.omicron, 1, stark.trace_degree(), combination.max_degree,
+        b"deep");
+    Setup { stark, constraints, boundary, trace, composition, deep }
+}
+
+fn ood_check(setup: &Setup, frame: &OodFrame) -> bool {
+    let combination = setup.stark.combination(&setup.constraints, &setup.boundary, b"combination");
+    setup.stark.ood_composition_value(&setup.constraints, &setup.boundary, &combination,
+        setup.deep.z, frame, None) == frame.composition
+}
+
+// degree of the polynomial behind the codeword fed to FRI
+fn deep_degree(setup: &Setup, frame: &OodFrame) -> Option<usize> {
+    let stark = &setup.stark;
+    let domain = stark.fri().eval_domain();
+    let trace = setup.trace.evaluate_coset(stark.generator, stark.omega, stark.fri_domain_length);
+    let composition = setup.composition.evaluate_coset(stark.generator, stark.omega, stark.fri_domain_length);
+    let codeword = setup.deep.composition_codeword(&domain, &[trace], &composition, frame);
+    Polynomial::interpolate_coset(&codeword, stark.generator, stark.omega).degree()
+}
+
+#[test]
+fn honest_frame_passes() {
+    let setup = setup();
+    let frame = setup.deep.open(std::slice::from_ref(&setup.trace), &setup.composition);
+    assert!(ood_check(&setup, &frame));
+    assert!(deep_degree(&setup, &frame).unwrap() <= setup.deep.combination.max_degree);
+}
+
+#[test]
+fn tampered_trace_value_fails_consistency_check() {
+    let setup = setup();
+    let honest = setup.deep.open(std::slice::from_ref(&setup.trace), &setup.composition);
+
+    let mut frame = honest.clone();
+    frame.current[0] = frame.current[0] + FieldElement::ONE;
+    assert!(!ood_check(&setup, &frame));
+
+    let mut frame = honest;
+    frame.next[0] = frame.next[0] + FieldElement::ONE;
+    assert!(!ood_check(&setup, &frame));
+}
+
+#[test]
+fn tampered_composition_value_is_not_low_degree() {
+    // a wrong composition value at `z` fails the consistency check, and the DEEP quotient of the
+    // composition is then no polynomial either, so FRI rejects it as well
+    let setup = setup();
+    let mut frame = setup.deep.open(std::slice::from_ref(&setup.trace), &setup.composition);
+    frame.composition = frame.composition + FieldElement::ONE;
+    assert!(!ood_check(&setup, &frame));
+    assert!(deep_degree(&setup, &frame).unwrap() > setup.deep.combination.max_degree);
+}
@@ -0,0 +1,166 @@
+use stark_anatomy::{FieldElement, Fri, FriConfigError, FriOptions, MerkleTree, Polynomial, ProofStream};
+
+mod common;
+use common::fe;
+
+// codeword over the FRI domain of a polynomial of the given degree
+fn codeword(fri: &Fri, degree: usize) -> Vec<FieldElement> {
+    let polynomial = Polynomial::new((0..=degree as u128).map(|i| fe(7 * i + 3)).collect());
+    polynomial.evaluate_coset(fe(fri.offset), fe(fri.omega), fri.domain_length as usize)
+}
+
+fn prove(fri: &Fri, codeword: &[FieldElement]) -> Vec<Vec<u8>> {
+    let mut proof_stream = ProofStream::new();
+    fri.prove(codeword, &mut proof_stream);
+    proof_stream.objects
+}
+
+fn verify(fri: &Fri, objects: Vec<Vec<u8>>) -> bool {
+    let mut proof_stream = ProofStream { objects, read_index: 0 };
+    fri.verify(&mut proof_stream, &mut Vec::new())
+}
+
+#[test]
+fn honest_proof_verifies_for_every_folding_factor() {
+    for folding_factor in [2, 4, 8] {
+        let fri = FriOptions::new(1024, 4, 4).with_folding_factor(folding_factor).build().unwrap();
+        assert!(fri.num_rounds() >= 2);
+        let codeword = codeword(&fri, fri.max_degree());
+        let mut proof_stream = ProofStream::new();
+        let indices = fri.prove(&codeword, &mut proof_stream);
+
+        let mut proof_stream = ProofStream { objects: proof_stream.objects, read_index: 0 };
+        let mut values = Vec::new();
+        assert!(fri.verify(&mut proof_stream, &mut values));
+        assert_eq!(values.len(), indices.len() * folding_factor as usize);
+        for (index, value) in values {
+            assert_eq!(value, codeword[index]);
+        }
+    }
+}
+
+#[test]
+fn codeword_one_degree_too_high_is_rejected() {
+    for folding_factor in [2, 4, 8] {
+        let fri = FriOptions::new(1024, 4, 4).with_folding_factor(folding_factor).build().unwrap();
+        let objects = prove(&fri, &codeword(&fri, fri.max_degree() + 1));
+        assert!(!verify(&fri, objects));
+    }
+}
+
+#[test]
+#[should_panic(expected = "last layer exceeds the degree bound")]
+fn last_layer_coefficients_of_too_high_a_degree_are_not_dropped() {
+    let fri = FriOptions::new(1024, 4, 4).with_last_layer_as_coefficients(true).build().unwrap();
+    prove(&fri, &codeword(&fri, fri.max_degree() + 1));
+}
+
+#[test]
+fn proof_with_grinding_verifies_and_rejects_a_bad_nonce() {
+    let fri = FriOptions::new(1024, 4, 4).with_grinding_bits(8).build().unwrap();
+    let objects = prove(&fri, &codeword(&fri, fri.max_degree()));
+    assert!(verify(&fri, objects.clone()));
+
+    // the nonce follows the layer caps and the last layer
+    let position = fri.num_rounds() as usize;
+    let seed = ProofStream { objects: objects[..position].to_vec(), read_index: position }.verifier_fiat_shamir();
+    let nonce = u64::from_le_bytes(objects[position].clone().try_into().unwrap());
+    assert!(Fri::check_nonce(&seed, nonce, fri.grinding_bits));
+    let bad_nonce = (0..).find(|&nonce| !Fri::check_nonce(&seed, nonce, fri.grinding_bits)).unwrap();
+    let mut tampered = objects;
+    tampered[position] = bad_nonce.to_le_bytes().to_vec();
+    assert!(!verify(&fri, tampered));
+}
+
+#[test]
+fn tampered_last_layer_is_rejected() {
+    for last_layer_as_coefficients in [false, true] {
+        let fri = FriOptions::new(1024, 4, 4)
+            .with_last_layer_as_coefficients(last_layer_as_coefficients)
+            .build()
+            .unwrap();
+        let objects = prove(&fri, &codeword(&fri, fri.max_degree()));
+        assert!(verify(&fri, objects.clone()));
+
+        // the last layer follows the caps of the committed layers
+        let position = fri.num_rounds() as usize - 1;
+        let mut last_layer = FieldElement::read_elements(&objects[position]).unwrap();
+        let expected_length = fri.last_layer_length() as usize;
+        if last_layer_as_coefficients {
+            assert_eq!(last_layer.len(), expected_length / fri.expansion_factor as usize);
+        } else {
+            assert_eq!(last_layer.len(), expected_length);
+        }
+
+        // a low degree change disagrees with the folded values at the queried points
+        for element in last_layer.iter_mut() {
+            *element = *element + FieldElement::ONE;
+        }
+        let mut tampered = objects;
+        tampered[position] = FieldElement::elements_as_bytes(&last_layer).to_vec();
+        assert!(!verify(&fri, tampered));
+    }
+}
+
+#[test]
+fn batch_without_queries_is_rejected() {
+    // 16 points with 3 tests leave a single round: nothing is queried, so a batch proof would
+    // never open the committed codewords and any commitment would pass with a zero last layer
+    assert!(matches!(FriOptions::new(16, 2, 3).build(), Err(FriConfigError::TooFewRounds(1))));
+    let omega = FieldElement::ZERO.primitive_nth_root(16);
+    let fri = Fri::new(FieldElement::GENERATOR.as_int(), omega.as_int(), 16, 2, 3);
+    assert_eq!(fri.num_rounds(), 1);
+
+    let garbage = (0..16u128).map(|i| fe(i * i * i * i * 31 + 5)).collect::<Vec<_>>();
+    let leafs = (0..8).map(|j| FieldElement::elements_as_bytes(&[garbage[j], garbage[j + 8]]).to_vec())
+        .collect::<Vec<_>>();
+    let objects = vec![
+        MerkleTree::new(&leafs).root().to_vec(),
+        FieldElement::elements_as_bytes(&[FieldElement::ZERO; 16]).to_vec(),
+    ];
+    let mut proof_stream = ProofStream { objects, read_index: 0 };
+    assert!(!fri.verify_batch(&[7], &mut proof_stream, &mut Vec::new()));
+}
+
+#[test]
+fn truncated_or_malformed_proof_is_rejected() {
+    let fri = FriOptions::new(256, 4, 4).with_grinding_bits(2).build().unwrap();
+    let objects = prove(&fri, &codeword(&fri, fri.max_degree()));
+    assert!(verify(&fri, objects.clone()));
+
+    for length in 0..objects.len() {
+        assert!(!verify(&fri, objects[..length].to_vec()));
+    }
+    for i in 0..objects.len() {
+        let mut malformed = objects.clone();
+        malformed[i].pop();
+        assert!(!verify(&fri, malformed));
+    }
+}
+
+#[test]
+fn build_reports_every_configuration_error() {
+    let error = |options: FriOptions| options.build().err().unwrap();
+    assert_eq!(error(FriOptions::new(1000, 4, 4)), FriConfigError::DomainLengthNotPowerOfTwo(1000));
+    assert_eq!(error(FriOptions::new(1 << 120, 4, 4)), FriConfigError::DomainTooLarge(1 << 120));
+    assert_eq!(error(FriOptions::new(1024, 3, 4)), FriConfigError::ExpansionFactorNotPowerOfTwo(3));
+    assert_eq!(error(FriOptions::new(1024, 1, 4)), FriConfigError::ExpansionFactorTooSmall(1));
+    assert_eq!(error(FriOptions::new(16, 16, 1)),
+        FriConfigError::ExpansionFactorTooLarge { expansion_factor: 16, domain_length: 16 });
+    let omega = FieldElement::ZERO.primitive_nth_root(2048).as_int();
+    assert_eq!(error(FriOptions::new(1024, 4, 4).with_omega(omega)),
+        FriConfigError::OmegaWrongOrder { omega, domain_length: 1024 });
+    assert_eq!(error(FriOptions::new(1024, 4, 4).with_offset(1)), FriConfigError::OffsetInSubgroup(1));
+    assert_eq!(error(FriOptions::new(1024, 4, 4).with_folding_factor(3)), FriConfigError::InvalidFoldingFactor(3));
+    assert_eq!(error(FriOptions::new(1024, 4, 4).with_merkle_arity(32)), FriConfigError::InvalidMerkleArity(32));
+    assert_eq!(error(FriOptions::new(1024, 4, 4).with_cap_height(10)),
+        FriConfigError::CapHeightTooLarge { cap_height: 10, depth: 9 });
+    assert_eq!(error(FriOptions::new(1024, 4, 4).with_merkle_arity(4).with_cap_height(6)),
+        FriConfigError::CapHeightTooLarge { cap_height: 6, depth: 5 });
+    assert!(FriOptions::new(1024, 4, 4).with_cap_height(9).build().is_ok());
+    assert_eq!(error(FriOptions::new(1024, 4, 4).with_grinding_bits(65)), FriConfigError::TooManyGrindingBits(65));
+    assert_eq!(error(FriOptions::new(1024, 4, 0)), FriConfigError::NoColinearityTests);
+    assert_eq!(error(FriOptions::new(1024, 4, 2000)),
+        FriConfigError::TooManyColinearityTests { num_colinearity_tests: 2000, last_layer_length: 1024 });
+    assert_eq!(error(FriOptions::new(16, 2, 3)), FriConfigError::TooFewRounds(1));
+}
@@ -0,0 +1,85 @@
+use stark_anatomy::{MPolynomial, Polynomial};
+
+mod common;
+use common::fe;
+
+#[test]
+fn representation_is_canonical() {
+    // padded exponent vectors and zero coefficients do not change the polynomial
+    let a = MPolynomial::new([(vec![1, 0, 0], fe(3)), (vec![0, 2], fe(0)), (vec![0, 0], fe(5))]);
+    let b = MPolynomial::new([(vec![], fe(5)), (vec![1], fe(3))]);
+    assert_eq!(a, b);
+    assert_eq!(a.num_variables(), 1);
+    assert_eq!(a.terms().keys().collect::<Vec<_>>(), vec![&vec![], &vec![1]]);
+
+    let x = MPolynomial::variables(3);
+    assert!((&x[2] - &x[2]).is_zero());
+    assert_eq!(MPolynomial::zero().degree(), None);
+    assert_eq!(MPolynomial::constant(fe(7)).degree(), Some(0));
+}
+
+#[test]
+fn operators_agree_with_evaluation() {
+    let x = MPolynomial::variables(3);
+    let a = &x[0] * &x[1] + MPolynomial::constant(fe(3)) * &x[2];
+    let b = (&x[2] ^ 3) - &x[0];
+    assert_eq!(a.num_variables(), 3);
+    assert_eq!(a.degree(), Some(2));
+    assert_eq!((&a * &b).degree(), Some(5));
+
+    let point = [fe(2), fe(5), fe(7)];
+    assert_eq!(a.evaluate(&point), fe(2 * 5 + 3 * 7));
+    assert_eq!((&a + &b).evaluate(&point), a.evaluate(&point) + b.evaluate(&point));
+    assert_eq!((&a - &b).evaluate(&point), a.evaluate(&point) - b.evaluate(&point));
+    assert_eq!((&a * &b).evaluate(&point), a.evaluate(&point) * b.evaluate(&point));
+    assert_eq!((-&a * fe(4)).evaluate(&point), -a.evaluate(&point) * fe(4));
+    assert_eq!((&a ^ 2).evaluate(&point), a.evaluate(&point) ^ 2);
+}
+
+#[test]
+fn lift_substitutes_a_variable() {
+    let poly = Polynomial::from(vec![fe(1), fe(0), fe(4)]);
+    let lifted = MPolynomial::lift(&poly, 2);
+    assert_eq!(lifted.num_variables(), 3);
+    assert_eq!(lifted.evaluate(&[fe(9), fe(9), fe(3)]), poly.evaluate(fe(3)));
+    assert!(MPolynomial::lift(&Polynomial::zero(), 1).is_zero());
+}
+
+#[test]
+fn symbolic_evaluation_matches_point_evaluation() {
+    // a transition constraint over [X, current, next] like the tutorial's
+    let x = MPolynomial::variables(3);
+    let constraint = (&x[2] - (&x[1] ^ 3) - MPolynomial::constant(fe(5)) * &x[0]) * (&x[1] ^ 2)
+        + MPolynomial::constant(fe(11));
+
+    let trace = Polynomial::from((0..100).map(|i| fe(i * i + 3)).collect::<Vec<_>>());
+    let next = trace.scale(fe(7));
+    let point = [Polynomial::from(vec![fe(0), fe(1)]), trace.clone(), next.clone()];
+    let transition = constraint.evaluate_symbolic(&point);
+    assert_eq!(transition.degree(), Some(5 * 99));
+
+    for z in [0, 1, 12345, 1 << 100] {
+        let z = fe(z);
+        let values = [z, trace.evaluate(z), next.evaluate(z)];
+        assert_eq!(transition.evaluate(z), constraint.evaluate(&values));
+    }
+    assert!(MPolynomial::zero().evaluate_symbolic(&point).is_zero());
+}
+
+#[test]
+fn exponent_overflow_is_detected() {
+    let x = MPolynomial::variables(2);
+    let big = &x[1] ^ (u128::MAX - 1);
+    assert_eq!(big.checked_mul(&x[1]), Some(&x[1] ^ u128::MAX));
+    assert_eq!(big.checked_mul(&(&x[1] ^ 2)), None);
+    assert_eq!(big.checked_mul(&x[0]), Some(&x[0] * &big));
+    assert_eq!((&x[1] ^ (1 << 126)).checked_pow(4), None);
+    assert_eq!((&x[1] ^ (1 << 126)).checked_pow(3), Some(&x[1] ^ (3 << 126)));
+}
+
+#[test]
+#[should_panic(expected = "exponent of the product does not fit into a u128")]
+fn exponent_overflow_panics_in_operators() {
+    let x = MPolynomial::variables(1);
+    let _ = (&x[0] ^ u128::MAX) * &x[0];
+}
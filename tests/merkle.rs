@@ -0,0 +1,296 @@
+use stark_anatomy::{FieldElement, FileStorage, Merkle, MerkleTree, RowMerkleTree, SaltedMerkleTree};
+use stark_anatomy::merkle_tree::SALT_BYTES;
+
+mod common;
+use common::CounterRng;
+
+fn leaves(number: usize) -> Vec<Vec<u8>> {
+    (0..number).map(|i| (i as u64).to_le_bytes().to_vec()).collect()
+}
+
+fn opened(data_array: &[Vec<u8>], indices: &[usize]) -> Vec<Vec<u8>> {
+    indices.iter().map(|&index| data_array[index].clone()).collect()
+}
+
+#[test]
+fn open_many_verifies() {
+    let data_array = leaves(64);
+    let root = Merkle::commit(&data_array);
+    for indices in [vec![0], vec![63], vec![5, 6], vec![3, 17, 18, 40, 41, 62], vec![9, 1, 9]] {
+        let proof = Merkle::open_many(&indices, &data_array);
+        assert!(Merkle::verify_many(64, root, &indices, &opened(&data_array, &indices), &proof));
+    }
+}
+
+#[test]
+fn open_many_of_single_leaf_tree_is_empty() {
+    let data_array = leaves(1);
+    let root = Merkle::commit(&data_array);
+    let proof = Merkle::open_many(&[0], &data_array);
+    assert!(proof.is_empty());
+    assert!(Merkle::verify_many(1, root, &[0], &data_array, &proof));
+}
+
+#[test]
+fn verify_many_rejects_tampering() {
+    let data_array = leaves(32);
+    let root = Merkle::commit(&data_array);
+    let indices = [2, 3, 11, 30];
+    let proof = Merkle::open_many(&indices, &data_array);
+    let values = opened(&data_array, &indices);
+
+    let mut wrong_values = values.clone();
+    wrong_values[2][0] ^= 1;
+    assert!(!Merkle::verify_many(32, root, &indices, &wrong_values, &proof));
+
+    let mut wrong_proof = proof.clone();
+    wrong_proof[0][0] ^= 1;
+    assert!(!Merkle::verify_many(32, root, &indices, &values, &wrong_proof));
+
+    assert!(!Merkle::verify_many(32, root, &[2, 3, 12, 30], &values, &proof));
+    assert!(!Merkle::verify_many(32, root, &indices, &values, &proof[1..]));
+
+    let mut long_proof = proof.clone();
+    long_proof.push([0; 32]);
+    assert!(!Merkle::verify_many(32, root, &indices, &values, &long_proof));
+
+    let duplicated = [2, 2];
+    let conflicting = vec![data_array[2].clone(), data_array[3].clone()];
+    assert!(!Merkle::verify_many(32, root, &duplicated, &conflicting, &Merkle::open_many(&duplicated, &data_array)));
+}
+
+#[test]
+fn verify_many_rejects_mismatched_lengths() {
+    let data_array = leaves(32);
+    let root = Merkle::commit(&data_array);
+    let indices = [2, 3, 11];
+    let proof = Merkle::open_many(&indices, &data_array);
+    let values = opened(&data_array, &indices);
+    assert!(Merkle::verify_many(32, root, &indices, &values, &proof));
+    assert!(!Merkle::verify_many(32, root, &indices, &values[..2], &proof));
+    assert!(!Merkle::verify_many(32, root, &indices[..2], &values, &proof));
+
+    let rows = (0..32u128).map(|i| vec![FieldElement::new(i)]).collect::<Vec<_>>();
+    let tree = RowMerkleTree::from_rows(rows);
+    let (rows, proof) = tree.open_many(&indices);
+    assert!(!RowMerkleTree::verify_many(32, tree.root(), &indices, &rows[..2], &proof));
+
+    let tree = SaltedMerkleTree::new(&data_array, &mut CounterRng(4));
+    let (salts, proof) = tree.open_many(&indices);
+    assert!(SaltedMerkleTree::verify_many(32, tree.root(), &indices, &salts, &values, &proof));
+    assert!(!SaltedMerkleTree::verify_many(32, tree.root(), &indices, &salts[..2], &values, &proof));
+    assert!(!SaltedMerkleTree::verify_many(32, tree.root(), &indices, &salts, &values[..2], &proof));
+}
+
+#[test]
+fn inner_node_cannot_be_opened_as_a_leaf() {
+    // the children of node 2 of a tree of 8 leaves, concatenated, form a 64-byte data element
+    // which would hash to that node if leaves and nodes were hashed alike
+    let data_array = leaves(8);
+    let tree = MerkleTree::new(&data_array);
+    let root = tree.root();
+    let (left, right) = (tree.prove(2)[1], tree.prove(0)[1]);
+    let forged = [left, right].concat();
+    let proof = vec![tree.prove(0)[2]];
+    assert!(!Merkle::verify_many(8, root, &[0], std::slice::from_ref(&forged), &proof));
+    assert!(!Merkle::verify_many(2, root, &[0], &[forged], &proof));
+}
+
+#[test]
+fn path_must_reach_the_leaf_level() {
+    let data_array = leaves(8);
+    let tree = MerkleTree::new(&data_array);
+    let path = tree.prove(0);
+    assert!(Merkle::verify(8, tree.root(), 0, &path, &data_array[0]));
+
+    // an empty path opens the root itself, and a path of one node a child of the root
+    let (left, right) = (tree.prove(4)[2], path[2]);
+    assert!(!Merkle::verify(8, tree.root(), 0, &[], &[left, right].concat()));
+    assert!(!Merkle::verify(8, tree.root(), 0, &path[2..], &[tree.prove(2)[1], path[1]].concat()));
+    assert!(!Merkle::verify(8, tree.root(), 0, &path[1..], &data_array[0]));
+    assert!(!Merkle::verify(8, tree.root(), 8, &path, &data_array[0]));
+    assert!(!Merkle::verify(16, tree.root(), 0, &path, &data_array[0]));
+    assert!(!Merkle::verify_with_cap(8, &tree.cap(1), 0, &path, &data_array[0]));
+    assert!(!Merkle::verify_with_cap(8, &tree.cap(1)[..1], 0, &path[..2], &data_array[0]));
+}
+
+#[test]
+fn open_many_is_never_larger_than_individual_paths() {
+    let data_array = leaves(256);
+    for indices in [vec![7], vec![0, 255], vec![10, 11, 12, 13], (0..40).map(|i| i * 6 + 1).collect()] {
+        let batched = Merkle::open_many(&indices, &data_array).len();
+        let individual: usize = indices.iter().map(|&index| Merkle::open(index, &data_array).len()).sum();
+        assert!(batched <= individual);
+    }
+}
+
+#[test]
+fn open_many_shares_nodes() {
+    let data_array = leaves(256);
+
+    // a single index needs its full path
+    assert_eq!(Merkle::open_many(&[100], &data_array).len(), 8);
+
+    // siblings only need the path of their parent
+    assert_eq!(Merkle::open_many(&[100, 101], &data_array).len(), 7);
+
+    // a complete subtree of 4 leaves needs the path of its root
+    assert_eq!(Merkle::open_many(&[100, 101, 102, 103], &data_array).len(), 6);
+
+    // two leaves in different halves share no node below the root
+    assert_eq!(Merkle::open_many(&[0, 255], &data_array).len(), 14);
+
+    // opening everything needs no sibling at all
+    let all = (0..256).collect::<Vec<usize>>();
+    assert!(Merkle::open_many(&all, &data_array).is_empty());
+}
+
+#[test]
+fn merkle_tree_matches_merkle() {
+    let data_array = leaves(128);
+    let tree = MerkleTree::new(&data_array);
+    assert_eq!(tree.root(), Merkle::commit(&data_array));
+    assert_eq!(tree.depth(), 7);
+    for index in [0, 1, 64, 127] {
+        let path = tree.prove(index);
+        assert!(Merkle::verify(128, tree.root(), index, &path, &data_array[index]));
+    }
+    let indices = [4, 5, 90, 127];
+    let proof = tree.prove_batch(&indices);
+    assert!(Merkle::verify_many(128, tree.root(), &indices, &opened(&data_array, &indices), &proof));
+
+    let hashed = (0..128).map(|index| tree.leaf(index)).collect();
+    assert_eq!(MerkleTree::from_leaf_hashes(hashed), tree);
+}
+
+#[test]
+fn row_merkle_tree_opens_full_rows() {
+    let columns = (0..3u128)
+        .map(|c| (0..16u128).map(|i| FieldElement::new(100 * c + i)).collect())
+        .collect::<Vec<Vec<FieldElement>>>();
+    let tree = RowMerkleTree::from_columns(&columns);
+    assert_eq!(tree.num_rows(), 16);
+    assert_eq!(tree.width(), 3);
+
+    let opening = tree.open(5);
+    assert_eq!(opening.values, vec![FieldElement::new(5), FieldElement::new(105), FieldElement::new(205)]);
+    assert!(RowMerkleTree::verify(16, tree.root(), 5, &opening));
+    assert!(Merkle::verify(16, tree.root(), 5, &opening.path, FieldElement::elements_as_bytes(&opening.values)));
+
+    let mut wrong = opening.clone();
+    wrong.values[1] = FieldElement::new(7);
+    assert!(!RowMerkleTree::verify(16, tree.root(), 5, &wrong));
+    assert!(!RowMerkleTree::verify(16, tree.root(), 4, &opening));
+
+    let indices = [1, 2, 9];
+    let (rows, proof) = tree.open_many(&indices);
+    assert!(RowMerkleTree::verify_many(16, tree.root(), &indices, &rows, &proof));
+}
+
+#[test]
+fn cap_shortens_paths() {
+    let data_array = leaves(64);
+    assert_eq!(Merkle::commit_cap(&data_array, 0), vec![Merkle::commit(&data_array)]);
+
+    let cap = Merkle::commit_cap(&data_array, 3);
+    assert_eq!(cap.len(), 8);
+    for index in [0, 9, 63] {
+        let path = Merkle::open_to_cap(index, &data_array, 3);
+        assert_eq!(path.len(), 3);
+        assert!(Merkle::verify_with_cap(64, &cap, index, &path, &data_array[index]));
+
+        // the high bits of the index select the cap node
+        assert!(!Merkle::verify_with_cap(64, &cap, index ^ 8, &path, &data_array[index]));
+    }
+}
+
+#[test]
+fn higher_arity_paths_verify() {
+    // 64 leaves are a power of both arities; with 32 leaves the level below the root is partial
+    for (num_leafs, arity, path_length) in [(64, 4, 9), (64, 8, 14), (32, 4, 7), (32, 8, 10)] {
+        let data_array = leaves(num_leafs);
+        let tree = MerkleTree::with_arity(&data_array, arity);
+        assert_eq!(MerkleTree::path_length(num_leafs, arity, 0), path_length);
+        for index in [0, 5, num_leafs - 1] {
+            let path = tree.prove(index);
+            assert_eq!(path.len(), path_length);
+            assert!(Merkle::verify_with_arity(arity, num_leafs, &[tree.root()], index, &path, &data_array[index]));
+            assert!(!Merkle::verify_with_arity(arity, num_leafs, &[tree.root()], index ^ 1, &path, &data_array[index]));
+            assert!(!Merkle::verify_with_arity(arity, num_leafs, &[tree.root()], index, &path[1..], &data_array[index]));
+        }
+
+        let cap = tree.cap(1);
+        assert_eq!(cap.len(), MerkleTree::cap_length(num_leafs, arity, 1));
+        let path = tree.prove_to_cap(9, 1);
+        assert!(Merkle::verify_with_arity(arity, num_leafs, &cap, 9, &path, &data_array[9]));
+
+        let indices = [1, 2, 3, 17, 30];
+        let proof = tree.prove_batch(&indices);
+        let values = opened(&data_array, &indices);
+        assert!(Merkle::verify_many_with_arity(arity, num_leafs, tree.root(), &indices, &values, &proof));
+        assert!(!Merkle::verify_many_with_arity(arity, num_leafs, tree.root(), &indices, &values, &proof[1..]));
+    }
+}
+
+#[test]
+fn salted_tree_hides_leaves() {
+    // two low-entropy data arrays differing in a single leaf
+    let data_array = vec![vec![0u8]; 16];
+    let mut other = data_array.clone();
+    other[3] = vec![1u8];
+
+    // with the same salts the commitments differ, but they are reproducible
+    let tree = SaltedMerkleTree::new(&data_array, &mut CounterRng(1));
+    assert_eq!(SaltedMerkleTree::new(&data_array, &mut CounterRng(1)), tree);
+    assert_ne!(SaltedMerkleTree::new(&other, &mut CounterRng(1)).root(), tree.root());
+
+    // equal data gives different leaf hashes: the path of leaf 0 starts with the hash of leaf 1
+    assert_ne!(tree.open(0).1[0], tree.open(1).1[0]);
+    assert_ne!(tree.root(), Merkle::commit(&data_array));
+    assert_ne!(SaltedMerkleTree::new(&data_array, &mut CounterRng(2)).root(), tree.root());
+
+    for index in [0, 3, 15] {
+        let (salt, path) = tree.open(index);
+        assert!(SaltedMerkleTree::verify(16, tree.root(), index, &salt, &path, &data_array[index]));
+        assert!(!SaltedMerkleTree::verify(16, tree.root(), index, &salt, &path, &other[3]));
+        assert!(!SaltedMerkleTree::verify(16, tree.root(), index, &[0; SALT_BYTES], &path, &data_array[index]));
+    }
+
+    let indices = [2, 3, 9];
+    let (salts, proof) = tree.open_many(&indices);
+    let values = opened(&data_array, &indices);
+    assert!(SaltedMerkleTree::verify_many(16, tree.root(), &indices, &salts, &values, &proof));
+    assert!(!SaltedMerkleTree::verify_many(16, tree.root(), &indices, &[salts[0]; 3], &values, &proof));
+
+    let tree = SaltedMerkleTree::with_arity(&data_array, 4, &mut CounterRng(3));
+    let (salt, path) = tree.open_to_cap(6, 1);
+    assert!(SaltedMerkleTree::verify_with_arity(4, 16, &tree.cap(1), 6, &salt, &path, &data_array[6]));
+    let (salts, proof) = tree.open_many(&indices);
+    assert!(SaltedMerkleTree::verify_many_with_arity(4, 16, tree.root(), &indices, &salts, &values, &proof));
+}
+
+#[test]
+fn file_backed_tree_matches_in_memory_tree() {
+    let path = std::env::temp_dir().join(format!("merkle-{}.nodes", std::process::id()));
+    let data_array = leaves(1 << 15);
+    for arity in [2, 8] {
+        let expected = MerkleTree::with_arity(&data_array, arity);
+        let storage = FileStorage::create(&path, data_array.len()).unwrap();
+        let leafs = data_array.iter().map(|data| Merkle::hash_leaf(data));
+        let tree = MerkleTree::build_in(storage, leafs, arity);
+        assert_eq!(tree.root(), expected.root());
+        assert_eq!(tree.cap(2), expected.cap(2));
+        for index in [0, 4097, (1 << 15) - 1] {
+            assert_eq!(tree.prove(index), expected.prove(index));
+        }
+        let indices = [3, 4, 1000, 20000];
+        assert_eq!(tree.prove_batch(&indices), expected.prove_batch(&indices));
+
+        // the file alone is enough to reopen the tree
+        drop(tree);
+        let tree = MerkleTree::from_storage(FileStorage::open(&path).unwrap(), arity);
+        let proof = tree.prove(77);
+        assert!(Merkle::verify_with_arity(arity, 1 << 15, &[tree.root()], 77, &proof, &data_array[77]));
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,90 @@
+use stark_anatomy::{FieldElement, Polynomial};
+
+fn poly(coefficients: &[u128]) -> Polynomial {
+    Polynomial::from(coefficients.iter().map(|&c| FieldElement::new(c)).collect::<Vec<_>>())
+}
+
+#[test]
+fn degree_distinguishes_zero_from_constants() {
+    assert_eq!(Polynomial::zero().degree(), None);
+    assert_eq!(poly(&[5]).degree(), Some(0));
+    assert!(!poly(&[5]).is_zero());
+
+    // trailing zeros are dropped
+    assert_eq!(poly(&[1, 2, 0, 0]), poly(&[1, 2]));
+    assert_eq!(poly(&[0, 0]), Polynomial::zero());
+    assert_eq!((poly(&[1, 1]) - poly(&[0, 1])).degree(), Some(0));
+}
+
+#[test]
+fn division_with_remainder() {
+    // x^3 + 2x + 7 = x * (x^2 + 1) + (x + 7)
+    let a = poly(&[7, 2, 0, 1]);
+    let b = poly(&[1, 0, 1]);
+    let (quotient, remainder) = a.divide(&b).unwrap();
+    assert_eq!(quotient, poly(&[0, 1]));
+    assert_eq!(remainder, poly(&[7, 1]));
+    assert_eq!(&quotient * &b + &remainder, a);
+    assert!(remainder.degree() < b.degree());
+    assert_eq!(&a / &b, quotient);
+    assert_eq!(&a % &b, remainder);
+
+    // exact division leaves no remainder
+    let product = &a * &b;
+    assert_eq!(&product / &b, a);
+    assert!((&product % &b).is_zero());
+
+    // dividing by a polynomial of higher degree
+    assert_eq!(b.divide(&a), Some((Polynomial::zero(), b.clone())));
+    assert_eq!(a.divide(&Polynomial::zero()), None);
+}
+
+#[test]
+fn operators_agree_with_evaluation() {
+    let a = poly(&[3, 0, 2]);
+    let b = poly(&[1, 5]);
+    let x = FieldElement::new(11);
+    assert_eq!((&a + &b).evaluate(x), a.evaluate(x) + b.evaluate(x));
+    assert_eq!((&a - &b).evaluate(x), a.evaluate(x) - b.evaluate(x));
+    assert_eq!((&a * &b).evaluate(x), a.evaluate(x) * b.evaluate(x));
+    assert_eq!((-&a).evaluate(x), -a.evaluate(x));
+    assert_eq!((&a ^ 3).evaluate(x), a.evaluate(x) ^ 3);
+    assert_eq!((&a - &a), Polynomial::zero());
+}
+
+#[test]
+fn interpolation_and_zerofier() {
+    let domain = [1, 2, 3, 4].map(FieldElement::new);
+    let values = [9, 0, 4, 4].map(FieldElement::new);
+    let interpolant = Polynomial::interpolate_domain(&domain, &values);
+    assert_eq!(interpolant.evaluate_domain(&domain), values);
+
+    let zerofier = Polynomial::zerofier_domain(&domain);
+    assert_eq!(zerofier.degree(), Some(4));
+    assert!(zerofier.evaluate_domain(&domain).iter().all(FieldElement::is_zero));
+
+    assert!(Polynomial::test_colinearity(&domain, &[5, 5, 5, 5].map(FieldElement::new)));
+    assert!(Polynomial::test_colinearity(&domain, &[1, 3, 5, 7].map(FieldElement::new)));
+    assert!(!Polynomial::test_colinearity(&domain, &values));
+}
+
+#[test]
+fn display() {
+    assert_eq!(Polynomial::zero().to_string(), "0");
+    assert_eq!(poly(&[5]).to_string(), "5");
+    assert_eq!(poly(&[5, 3, 1]).to_string(), "x^2 + 3*x + 5");
+    assert_eq!(poly(&[0, 1, 0, 2]).to_string(), "2*x^3 + x");
+}
+
+#[test]
+fn large_products_agree_with_evaluation() {
+    // long enough factors are multiplied with NTTs
+    let a = Polynomial::from((0..300u128).map(|i| FieldElement::new(i * 7 + 1)).collect::<Vec<_>>());
+    let b = Polynomial::from((0..200u128).map(|i| FieldElement::new(i ^ 0x55)).collect::<Vec<_>>());
+    let product = &a * &b;
+    assert_eq!(product.degree(), Some(299 + 199));
+    for x in [0, 3, 1 << 90] {
+        let x = FieldElement::new(x);
+        assert_eq!(product.evaluate(x), a.evaluate(x) * b.evaluate(x));
+    }
+}
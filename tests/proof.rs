@@ -0,0 +1,87 @@
+use stark_anatomy::{FriProof, OodFrame, Polynomial, ProofStream, RowMerkleTree, Stark, StarkProof};
+
+mod common;
+use common::fe;
+
+// a proof with every component filled in: a genuine FRI proof, and openings of trees over
+// arbitrary columns of the FRI domain
+fn proof(stark: &Stark, num_quotients: usize) -> StarkProof {
+    let fri = stark.fri();
+    let polynomial = Polynomial::new((0..=fri.max_degree() as u128).map(|i| fe(5 * i + 1)).collect());
+    let codeword = polynomial.evaluate_coset(stark.generator, stark.omega, stark.fri_domain_length);
+    let mut proof_stream = ProofStream::new();
+    let indices = fri.prove(&codeword, &mut proof_stream);
+    let mut proof_stream = ProofStream { objects: proof_stream.objects, read_index: 0 };
+    let fri_proof = FriProof::read(&fri, &mut proof_stream).unwrap();
+
+    let column = |seed: u128| (0..stark.fri_domain_length as u128).map(|i| fe(seed * 1000 + i)).collect();
+    let trace = RowMerkleTree::from_columns(&(0..stark.num_registers as u128).map(column).collect::<Vec<_>>());
+    let randomizer = RowMerkleTree::from_columns(&[column(7)]);
+    let quotients = (0..num_quotients as u128)
+        .map(|i| RowMerkleTree::from_columns(&[column(10 + i)]))
+        .collect::<Vec<_>>();
+
+    let num_leafs = stark.fri_domain_length / stark.folding_factor;
+    let positions = indices.iter()
+        .flat_map(|&index| (0..stark.folding_factor).map(move |t| index + t * num_leafs))
+        .collect::<Vec<_>>();
+    assert_eq!(positions.len(), StarkProof::num_opened_positions(stark));
+    let next = |position: usize| (position + stark.expansion_factor) % stark.fri_domain_length;
+
+    StarkProof {
+        trace_root: trace.root(),
+        randomizer_root: stark.zk.then(|| randomizer.root()),
+        quotient_roots: quotients.iter().map(RowMerkleTree::root).collect(),
+        ood_frame: OodFrame {
+            current: (0..stark.num_registers as u128).map(|i| fe(100 + i)).collect(),
+            next: (0..stark.num_registers as u128).map(|i| fe(200 + i)).collect(),
+            composition: fe(300),
+        },
+        ood_randomizer_value: stark.zk.then(|| fe(400)),
+        fri_proof,
+        trace_rows: positions.iter().map(|&position| trace.open(position)).collect(),
+        next_trace_rows: positions.iter().map(|&position| trace.open(next(position))).collect(),
+        randomizer_rows: match stark.zk {
+            true => positions.iter().map(|&position| randomizer.open(position)).collect(),
+            false => Vec::new(),
+        },
+        quotient_rows: quotients.iter()
+            .map(|tree| positions.iter().map(|&position| tree.open(position)).collect())
+            .collect(),
+    }
+}
+
+#[test]
+fn stark_proof_round_trips() {
+    for zk in [false, true] {
+        let stark = Stark::new(4, 2, 4, 2, 8, 2, zk);
+        let proof = proof(&stark, 3);
+        let mut proof_stream = proof.to_proof_stream();
+        let size = proof_stream.objects.iter().map(Vec::len).sum::<usize>();
+        assert_eq!(proof.size().total(), size);
+
+        let read = StarkProof::read(&stark, 3, &mut proof_stream).unwrap();
+        assert_eq!(proof_stream.read_index, proof_stream.objects.len());
+        assert_eq!(read.trace_root, proof.trace_root);
+        assert_eq!(read.randomizer_root, proof.randomizer_root);
+        assert_eq!(read.quotient_roots, proof.quotient_roots);
+        assert_eq!(read.ood_frame, proof.ood_frame);
+        assert_eq!(read.ood_randomizer_value, proof.ood_randomizer_value);
+        assert_eq!(read.fri_proof, proof.fri_proof);
+        assert_eq!(read.trace_rows, proof.trace_rows);
+        assert_eq!(read.next_trace_rows, proof.next_trace_rows);
+        assert_eq!(read.randomizer_rows, proof.randomizer_rows);
+        assert_eq!(read.quotient_rows, proof.quotient_rows);
+        assert_eq!(read, proof);
+    }
+}
+
+#[test]
+fn truncated_stark_proof_is_rejected() {
+    let stark = Stark::new(4, 2, 4, 2, 8, 2, true);
+    let objects = proof(&stark, 2).to_proof_stream().objects;
+    for length in 0..objects.len() {
+        let mut proof_stream = ProofStream { objects: objects[..length].to_vec(), read_index: 0 };
+        assert!(StarkProof::read(&stark, 2, &mut proof_stream).is_err());
+    }
+}
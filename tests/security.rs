@@ -0,0 +1,42 @@
+use stark_anatomy::{SecurityLevel, Stark};
+use stark_anatomy::security::{deep_ali_security, field_bits};
+
+#[test]
+fn stark_security_matches_known_values() {
+    // (expansion factor, colinearity checks, cycles, constraint degree, conjectured, proven); the
+    // first is bound by the field, the others by the queries
+    let cases = [
+        (4, 80, 1024, 2, 113, 73),
+        (4, 32, 1024, 2, 63, 30),
+        (16, 40, 4096, 3, 108, 70),
+    ];
+    for (expansion_factor, num_colinearity_checks, num_cycles, degree, conjectured, proven) in cases {
+        let stark = Stark::new(expansion_factor, num_colinearity_checks, 64, 1, num_cycles, degree, false);
+        assert_eq!(stark.security_level(), SecurityLevel { conjectured, proven });
+    }
+}
+
+#[test]
+fn stark_security_is_bounded_by_deep_ali() {
+    // the out-of-domain check over polynomials of degree below 2^11 with a list of one codeword
+    let bits = deep_ali_security(1 << 11, 1.0);
+    assert!((bits - (field_bits() - 11.0)).abs() < 1e-9);
+
+    let stark = Stark::new(4, 80, 64, 1, 1024, 2, false);
+    assert_eq!(stark.omicron_domain_length, 1 << 11);
+    let security = stark.security_level();
+    assert!(security.conjectured as f64 <= bits - 1.0);
+    assert!(security.proven <= security.conjectured);
+    assert!(security.conjectured <= stark.fri().security_level().conjectured);
+    assert!(security.proven <= stark.fri().security_level().proven);
+}
+
+#[test]
+fn folding_factor_weakens_the_commit_phase() {
+    // bound by the field: a bad challenge is `folding_factor - 1` times as likely
+    let expected = [(2, 113, 73), (4, 112, 73), (8, 110, 72), (16, 109, 72)];
+    for (folding_factor, conjectured, proven) in expected {
+        let stark = Stark::new(4, 80, 64, 1, 1024, 2, false).with_folding_factor(folding_factor);
+        assert_eq!(stark.security_level(), SecurityLevel { conjectured, proven });
+    }
+}
@@ -0,0 +1,73 @@
+use stark_anatomy::SparseMerkleTree;
+
+fn key(i: u8) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    key[0] = i;
+    key[31] = i.wrapping_mul(37);
+    key
+}
+
+#[test]
+fn insert_get_and_remove() {
+    let mut tree = SparseMerkleTree::new();
+    assert_eq!(tree.root(), SparseMerkleTree::empty_root());
+
+    assert_eq!(tree.insert(key(1), b"10".to_vec()), None);
+    assert_eq!(tree.insert(key(2), b"20".to_vec()), None);
+    let root = tree.root();
+    assert_eq!(tree.insert(key(1), b"11".to_vec()), Some(b"10".to_vec()));
+    assert_eq!(tree.get(&key(1)), Some(&b"11"[..]));
+    assert_eq!(tree.get(&key(3)), None);
+    assert_ne!(tree.root(), root);
+
+    // the root depends on the contents only
+    assert_eq!(tree.insert(key(1), b"10".to_vec()), Some(b"11".to_vec()));
+    assert_eq!(tree.root(), root);
+    assert_eq!(tree.remove(&key(1)), Some(b"10".to_vec()));
+    assert_eq!(tree.remove(&key(2)), Some(b"20".to_vec()));
+    assert!(tree.is_empty());
+    assert_eq!(tree.root(), SparseMerkleTree::empty_root());
+}
+
+#[test]
+fn proofs_of_inclusion_and_non_inclusion() {
+    let mut tree = SparseMerkleTree::new();
+    for i in 0..20 {
+        tree.insert(key(i), vec![i; 3]);
+    }
+    let root = tree.root();
+
+    for i in [0, 7, 19] {
+        let proof = tree.prove(&key(i));
+        assert!(SparseMerkleTree::verify_inclusion(root, &key(i), &[i; 3], &proof));
+        assert!(!SparseMerkleTree::verify_inclusion(root, &key(i), &[i; 2], &proof));
+        assert!(!SparseMerkleTree::verify_non_inclusion(root, &key(i), &proof));
+    }
+
+    let absent = key(200);
+    let proof = tree.prove(&absent);
+    assert!(proof.siblings.len() < 256);
+    assert!(SparseMerkleTree::verify_non_inclusion(root, &absent, &proof));
+    assert!(!SparseMerkleTree::verify_non_inclusion(root, &key(7), &proof));
+
+    let mut wrong = proof.clone();
+    wrong.siblings.pop();
+    assert!(!SparseMerkleTree::verify_non_inclusion(root, &absent, &wrong));
+}
+
+#[test]
+fn batched_updates_match_single_updates() {
+    let mut single = SparseMerkleTree::new();
+    let mut batched = SparseMerkleTree::new();
+    let updates = (0..50)
+        .map(|i| (key(i % 30), if i % 7 == 0 { None } else { Some(vec![i]) }))
+        .collect::<Vec<_>>();
+    for (key, value) in updates.iter().cloned() {
+        match value {
+            Some(value) => single.insert(key, value),
+            None => single.remove(&key),
+        };
+    }
+    assert_eq!(batched.update_batch(updates), single.root());
+    assert_eq!(batched, single);
+}
@@ -0,0 +1,187 @@
+use stark_anatomy::{DeepComposition, FieldElement, MPolynomial, Polynomial, ProofStream, Stark, StarkProof};
+
+mod common;
+use common::{fe, CounterRng};
+
+// every point at which the verifier sees the trace polynomial: the cosets of the first FRI layer
+// opened by the given queries, at `x` and at `omicron * x`, and the DEEP points `z`, `omicron * z`
+fn opened_points(stark: &Stark, queries: &[usize], z: FieldElement) -> Vec<FieldElement> {
+    let num_leafs = stark.fri_domain_length / stark.folding_factor;
+    let mut points = Vec::new();
+    for &query in queries {
+        for t in 0..stark.folding_factor {
+            let x = stark.generator * (stark.omega ^ (query + t * num_leafs) as u128);
+            points.push(x);
+            points.push(stark.omicron * x);
+        }
+    }
+    points.push(z);
+    points.push(stark.omicron * z);
+    points
+}
+
+#[test]
+fn randomizers_cover_all_openings() {
+    for folding_factor in [2, 4, 8] {
+        let stark = Stark::new(4, 3, 6, 2, 16, 2, true).with_folding_factor(folding_factor);
+        let openings = 2 * StarkProof::num_opened_positions(&stark) + 2;
+        assert_eq!(stark.num_randomizers, openings);
+        assert_eq!(stark.randomized_trace_length, 16 + openings);
+        assert_eq!(stark.fri().folding_factor, folding_factor as u128);
+    }
+    assert_eq!(Stark::new(4, 3, 6, 2, 16, 2, false).num_randomizers, 0);
+}
+
+#[test]
+fn openings_of_different_witnesses_are_identically_distributed() {
+    let stark = Stark::new(4, 2, 4, 1, 8, 2, true);
+    let witness_a = (0..8u128).map(|i| vec![fe(i * i)]).collect::<Vec<_>>();
+    let witness_b = (0..8u128).map(|i| vec![fe(1000 + 7 * i)]).collect::<Vec<_>>();
+
+    let trace_domain = stark.omicron_domain()[..stark.randomized_trace_length].to_vec();
+    let column = |trace: &[Vec<FieldElement>]| trace.iter().map(|row| row[0]).collect::<Vec<_>>();
+    let trace_a = stark.randomize_trace(&witness_a, &mut CounterRng(3));
+    let polynomial_a = Polynomial::interpolate_domain(&trace_domain, &column(&trace_a));
+
+    let z = DeepComposition::sample_point(b"z", stark.omicron_domain_length, stark.generator,
+        stark.fri_domain_length);
+    let points = opened_points(&stark, &[3, 17], z);
+    assert_eq!(points.len(), stark.num_randomizers);
+    let view = points.iter().map(|&x| polynomial_a.evaluate(x)).collect::<Vec<_>>();
+
+    // for a fixed witness the view is an affine function of the random rows, with as many inputs
+    // as outputs; it is onto, hence a bijection mapping uniform rows to a uniform view, iff any
+    // view can be produced, which is checked here by reproducing the view of A with witness B
+    let num_cycles = stark.original_trace_length;
+    let mut domain = trace_domain[..num_cycles].to_vec();
+    domain.extend_from_slice(&points);
+    let mut values = column(&witness_b);
+    values.extend_from_slice(&view);
+    let polynomial_b = Polynomial::interpolate_domain(&domain, &values);
+    let random_rows = trace_domain[num_cycles..].iter()
+        .map(|&x| vec![polynomial_b.evaluate(x)])
+        .collect::<Vec<_>>();
+
+    let mut trace_b = witness_b.clone();
+    trace_b.extend(random_rows);
+    let polynomial_b = Polynomial::interpolate_domain(&trace_domain, &column(&trace_b));
+    assert_eq!(column(&trace_b[..num_cycles]), column(&witness_b));
+    assert_eq!(points.iter().map(|&x| polynomial_b.evaluate(x)).collect::<Vec<_>>(), view);
+    assert_ne!(polynomial_a, polynomial_b);
+}
+
+#[test]
+#[should_panic(expected = "expansion factor must be 4 or greater")]
+fn zero_expansion_factor_is_rejected() {
+    Stark::new(0, 2, 4, 1, 8, 2, false);
+}
+
+#[test]
+fn randomizer_has_the_degree_of_the_fri_run() {
+    let stark = Stark::new(4, 2, 4, 1, 8, 2, true);
+    let randomizer = stark.randomizer_polynomial(&mut CounterRng(5)).unwrap();
+    assert_eq!(randomizer.degree(), Some(stark.fri().max_degree()));
+    assert!(Stark::new(4, 2, 4, 1, 8, 2, false).randomizer_polynomial(&mut CounterRng(5)).is_none());
+}
+
+// two registers with `a' = b` and `b' = a * b + 1`, starting from `(1, 2)`
+struct Computation {
+    trace: Vec<Vec<FieldElement>>,
+    constraints: Vec<MPolynomial>,
+    boundary: Vec<(usize, usize, FieldElement)>,
+}
+
+fn computation() -> Computation {
+    let mut trace = vec![vec![fe(1), fe(2)]];
+    for _ in 1..8 {
+        let row = trace.last().unwrap();
+        trace.push(vec![row[1], row[0] * row[1] + fe(1)]);
+    }
+    let x = MPolynomial::variables(5);
+    let constraints = vec![&x[3] - &x[2], &x[4] - &x[1] * &x[2] - MPolynomial::constant(fe(1))];
+    let boundary = vec![(0, 0, fe(1)), (0, 1, fe(2)), (7, 1, trace[7][1])];
+    Computation { trace, constraints, boundary }
+}
+
+fn prove(stark: &Stark) -> ProofStream {
+    let Computation { trace, constraints, boundary } = computation();
+    let mut proof_stream = ProofStream::new();
+    stark.prove(&trace, &constraints, &boundary, &mut CounterRng(11), &mut proof_stream);
+    proof_stream
+}
+
+// rewrites the proof in the stream after applying `tamper` to it
+fn tampered(stark: &Stark, proof_stream: &ProofStream, tamper: impl Fn(&mut StarkProof)) -> ProofStream {
+    let mut proof_stream = ProofStream { objects: proof_stream.objects.clone(), read_index: 0 };
+    let mut proof = StarkProof::read(stark, 2 + stark.num_registers, &mut proof_stream).unwrap();
+    tamper(&mut proof);
+    proof.to_proof_stream()
+}
+
+#[test]
+fn honest_proof_is_accepted() {
+    let Computation { constraints, boundary, .. } = computation();
+    for zk in [false, true] {
+        let stark = Stark::new(4, 2, 4, 2, 8, 2, zk);
+        let mut proof_stream = prove(&stark);
+        assert!(stark.verify(&mut proof_stream, &constraints, &boundary));
+        assert_eq!(proof_stream.read_index, proof_stream.objects.len());
+    }
+}
+
+#[test]
+fn tampered_ood_frame_is_rejected() {
+    let Computation { constraints, boundary, .. } = computation();
+    for zk in [false, true] {
+        let stark = Stark::new(4, 2, 4, 2, 8, 2, zk);
+        let proof_stream = prove(&stark);
+        let tamperings: [fn(&mut StarkProof); 3] = [
+            |proof| proof.ood_frame.current[0] = proof.ood_frame.current[0] + FieldElement::ONE,
+            |proof| proof.ood_frame.next[1] = proof.ood_frame.next[1] + FieldElement::ONE,
+            |proof| proof.ood_frame.composition = proof.ood_frame.composition + FieldElement::ONE,
+        ];
+        for tamper in tamperings {
+            let mut proof_stream = tampered(&stark, &proof_stream, tamper);
+            assert!(!stark.verify(&mut proof_stream, &constraints, &boundary));
+        }
+    }
+}
+
+#[test]
+fn tampered_randomizer_is_rejected() {
+    let Computation { constraints, boundary, .. } = computation();
+    let stark = Stark::new(4, 2, 4, 2, 8, 2, true);
+    let proof_stream = prove(&stark);
+    let tamperings: [fn(&mut StarkProof); 2] = [
+        |proof| proof.ood_randomizer_value = proof.ood_randomizer_value.map(|v| v + FieldElement::ONE),
+        |proof| proof.randomizer_rows[0].values[0] = proof.randomizer_rows[0].values[0] + FieldElement::ONE,
+    ];
+    for tamper in tamperings {
+        let mut proof_stream = tampered(&stark, &proof_stream, tamper);
+        assert!(!stark.verify(&mut proof_stream, &constraints, &boundary));
+    }
+}
+
+#[test]
+fn proof_of_another_statement_is_rejected() {
+    let Computation { constraints, mut boundary, .. } = computation();
+    let stark = Stark::new(4, 2, 4, 2, 8, 2, true);
+    let mut proof_stream = prove(&stark);
+    boundary[2].2 = boundary[2].2 + FieldElement::ONE;
+    assert!(!stark.verify(&mut proof_stream, &constraints, &boundary));
+}
+
+#[test]
+fn transition_zerofier_vanishes_on_the_constrained_rows() {
+    let stark = Stark::new(4, 2, 4, 2, 8, 2, true);
+    let zerofier = stark.transition_zerofier();
+    assert_eq!(zerofier.degree(), 7);
+    for row in 0..stark.randomized_trace_length {
+        assert_eq!(zerofier.evaluate(stark.omicron ^ row as u128).is_zero(), row < 7);
+    }
+    let codeword = zerofier.evaluate_coset(stark.generator, stark.omega, stark.fri_domain_length);
+    for position in [0, 5, 131] {
+        let x = stark.generator * (stark.omega ^ position as u128);
+        assert_eq!(codeword[position], zerofier.evaluate(x));
+    }
+}
@@ -0,0 +1,90 @@
+use stark_anatomy::{FieldElement, Polynomial, Zerofier};
+
+mod common;
+use common::fe;
+
+// the product of `X - omega^i` over all rows which are not excluded
+fn dense(omega: FieldElement, order: usize, excluded: &[usize]) -> Polynomial {
+    let roots = (0..order)
+        .filter(|i| !excluded.contains(i))
+        .map(|i| omega ^ (i as u128))
+        .collect::<Vec<_>>();
+    Polynomial::zerofier_domain(&roots)
+}
+
+fn zerofiers(omega: FieldElement, order: usize) -> Vec<(Zerofier, Vec<usize>)> {
+    vec![
+        (Zerofier::new(omega, order), vec![]),
+        (Zerofier::transition(omega, order), vec![order - 1]),
+        (Zerofier::excluding_last(omega, order, 3), vec![order - 3, order - 2, order - 1]),
+        (Zerofier::excluding(omega, order, &[11, 2, 5, 2]), vec![2, 5, 11]),
+        // more than log2(order) excluded rows, which switches to the dense form
+        (Zerofier::excluding_last(omega, order, 11), (5..16).collect()),
+        (Zerofier::excluding(omega, order, &[0, 3, 4, 7, 9, 15]), vec![0, 3, 4, 7, 9, 15]),
+    ]
+}
+
+#[test]
+fn polynomial_matches_dense_product() {
+    let order = 16;
+    let omega = FieldElement::ZERO.primitive_nth_root(order as u128);
+    for (zerofier, excluded) in zerofiers(omega, order) {
+        assert_eq!(zerofier.excluded_rows(), excluded.as_slice());
+        let expected = dense(omega, order, &excluded);
+        assert_eq!(zerofier.polynomial(), expected);
+        assert_eq!(expected.degree(), Some(zerofier.degree()));
+    }
+}
+
+#[test]
+fn evaluate_matches_dense_product() {
+    let order = 16;
+    let omega = FieldElement::ZERO.primitive_nth_root(order as u128);
+    for (zerofier, excluded) in zerofiers(omega, order) {
+        let expected = dense(omega, order, &excluded);
+        // points off the subgroup, and every point of it, where the sparse form is 0 / 0 for the
+        // excluded rows
+        let points = [fe(0), fe(1 << 100), FieldElement::GENERATOR].into_iter()
+            .chain((0..order).map(|i| omega ^ (i as u128)));
+        for point in points {
+            assert_eq!(zerofier.evaluate(point), expected.evaluate(point));
+        }
+    }
+}
+
+#[test]
+fn evaluate_coset_matches_dense_product() {
+    let order = 16;
+    let omega = FieldElement::ZERO.primitive_nth_root(order as u128);
+    let length = 64;
+    let coset_omega = FieldElement::ZERO.primitive_nth_root(length as u128);
+    for (zerofier, excluded) in zerofiers(omega, order) {
+        let expected = dense(omega, order, &excluded);
+        // a proper coset, and the subgroup of order 64 itself, which contains the whole subgroup
+        // of the zerofier
+        for offset in [FieldElement::GENERATOR, FieldElement::ONE] {
+            let values = zerofier.evaluate_coset(offset, coset_omega, length);
+            assert_eq!(values, expected.evaluate_coset(offset, coset_omega, length));
+        }
+    }
+}
+
+#[test]
+fn dense_form_matches_sparse_evaluation() {
+    // a short trace in a large subgroup, as for transition constraints of high degree; the kept
+    // factors are multiplied with NTTs
+    let order = 512;
+    let omega = FieldElement::ZERO.primitive_nth_root(order as u128);
+    let zerofier = Zerofier::excluding_last(omega, order, 300);
+    let expected = dense(omega, order, &(212..512).collect::<Vec<_>>());
+    assert_eq!(zerofier.polynomial(), expected);
+
+    let length = 2048;
+    let coset_omega = FieldElement::ZERO.primitive_nth_root(length as u128);
+    let values = zerofier.evaluate_coset(FieldElement::GENERATOR, coset_omega, length);
+    for i in [0, 1, 777, length - 1] {
+        let point = FieldElement::GENERATOR * (coset_omega ^ (i as u128));
+        assert_eq!(values[i], zerofier.evaluate(point));
+        assert_eq!(values[i], expected.evaluate(point));
+    }
+}